@@ -0,0 +1,21 @@
+use error::{Error, Result};
+use gif::{DisposalMethod, Encoder, Frame, Repeat, SetParameter};
+use sfml::graphics::Image;
+use std::io::Write;
+
+/// Encodes the images into a looping GIF, the delay is given in hundredths of a second
+pub fn encode_gif<W: Write>(frames: &[Image], delay: u16, writer: W) -> Result<()> {
+    let size = frames.first().ok_or(Error::InternalError)?.size();
+    let mut encoder = Encoder::new(writer, size.x as u16, size.y as u16, &[])?;
+    encoder.set(Repeat::Infinite)?;
+
+    for image in frames {
+        let mut pixels = image.pixel_data().to_vec();
+        let mut frame = Frame::from_rgba(size.x as u16, size.y as u16, &mut pixels);
+        frame.delay = delay;
+        // the previous frame must not show through the transparent corners
+        frame.dispose = DisposalMethod::Background;
+        encoder.write_frame(&frame)?;
+    }
+    Ok(())
+}
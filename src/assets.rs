@@ -8,21 +8,23 @@ use unitypack::engine::EngineObject;
 use unitypack::engine::mesh::{IntoMesh, Mesh};
 use unitypack::asset::Asset;
 use unitypack::assetbundle::Signature;
-use sfml::graphics::RenderTexture;
 use error::{Error, Result};
 use cards::*;
 use std::collections::HashMap;
 use glob::glob;
 use rayon::prelude::*;
-use builder::Builder;
 use std::path::Path;
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Fonts {
     Belwe,
     BelweOutline,
     BlizzardGlobal,
     FranklinGothic,
+    /// Replacement of Belwe for locales with non-latin scripts
+    LocalizedTitle(Locale),
+    /// Replacement of Franklin Gothic for locales with non-latin scripts
+    LocalizedBody(Locale),
 }
 
 const FONT_BELWE: &'static str = "Belwe";
@@ -30,11 +32,48 @@ const FONT_BELWE_OUTLINE: &'static str = "Belwe_Outline";
 const FONT_BLIZZARDGLOBAL: &'static str = "BlizzardGlobal";
 const FONT_FRANKLINGOTHIC: &'static str = "FranklinGothic";
 
+/// Locales shipping their own fonts, named like "Belwe_zhCN" and "FranklinGothic_zhCN"
+pub const LOCALIZED_FONT_LOCALES: [Locale; 6] = [
+    Locale::ZhCn,
+    Locale::ZhTw,
+    Locale::JaJp,
+    Locale::KoKr,
+    Locale::ThTh,
+    Locale::RuRu,
+];
+
+impl Fonts {
+    /// Whether the font has a glyph for the character
+    pub fn covers(&self, c: char) -> bool {
+        let code = c as u32;
+        // basic latin, latin extensions and general punctuation
+        let latin = code < 0x0250 || (code >= 0x2000 && code <= 0x206F);
+        let cjk = (code >= 0x2E80 && code <= 0x9FFF) || (code >= 0xF900 && code <= 0xFAFF)
+            || (code >= 0xFF00 && code <= 0xFFEF);
+        match *self {
+            Fonts::Belwe | Fonts::BelweOutline | Fonts::FranklinGothic => latin,
+            // last resort, covers most of the scripts
+            Fonts::BlizzardGlobal => true,
+            Fonts::LocalizedTitle(locale) | Fonts::LocalizedBody(locale) => {
+                latin || match locale {
+                    Locale::ZhCn | Locale::ZhTw | Locale::JaJp => cjk,
+                    Locale::KoKr => {
+                        cjk || (code >= 0xAC00 && code <= 0xD7AF)
+                            || (code >= 0x1100 && code <= 0x11FF)
+                    }
+                    Locale::ThTh => code >= 0x0E00 && code <= 0x0E7F,
+                    Locale::RuRu => code >= 0x0400 && code <= 0x052F,
+                    _ => false,
+                }
+            }
+        }
+    }
+}
+
 /// Stores graphic elements to construct cards
 pub struct Assets {
     portraits: (HashMap<String, String>, HashMap<String, ObjectLocator>), // cards, textures
     pub textures: HashMap<String, String>,
-    card_frames: HashMap<String, RenderTexture>,
     fonts: HashMap<Fonts, Font>,
     pub meshes: HashMap<String, Mesh>,
 }
@@ -491,14 +530,12 @@ impl Assets {
         let meshes = Assets::load_meshes(assets_path)?;
         let portraits = Assets::load_portraits(assets_path)?;
         let textures = Assets::load_textures(assets_path)?;
-        let card_frames = Assets::load_card_frames(&textures, &meshes)?;
         let fonts = Assets::load_fonts(assets_path)?;
 
 
         Ok(Assets {
             portraits: portraits,
             textures: textures,
-            card_frames: card_frames,
             fonts: fonts,
             meshes: meshes,
         })
@@ -583,60 +620,31 @@ impl Assets {
         Ok(textures)
     }
 
-    fn load_card_frames(
-        textures: &HashMap<String, String>,
-        meshes: &HashMap<String, Mesh>,
-    ) -> Result<HashMap<String, RenderTexture>> {
-        let mut res = HashMap::new();
-        let builder = Builder::new()?;
-        {
-            res.insert(
-                format!("{:?}_{:?}", CardType::Spell, CardClass::Mage),
-                builder.build_card_frame(textures, &meshes, &CardClass::Mage, &CardType::Spell)?,
-            );
-            res.insert(
-                format!("{:?}_{:?}", CardType::Spell, CardClass::Priest),
-                builder.build_card_frame(textures, &meshes, &CardClass::Priest, &CardType::Spell)?,
-            );
-            res.insert(
-                format!("{:?}_{:?}", CardType::Spell, CardClass::Warrior),
-                builder.build_card_frame(textures, &meshes, &CardClass::Warrior, &CardType::Spell)?,
-            );
-            res.insert(
-                format!("{:?}_{:?}", CardType::Spell, CardClass::Hunter),
-                builder.build_card_frame(textures, &meshes, &CardClass::Hunter, &CardType::Spell)?,
-            );
-            res.insert(
-                format!("{:?}_{:?}", CardType::Spell, CardClass::Warlock),
-                builder.build_card_frame(textures, &meshes, &CardClass::Warlock, &CardType::Spell)?,
-            );
-            res.insert(
-                format!("{:?}_{:?}", CardType::Spell, CardClass::Rogue),
-                builder.build_card_frame(textures, &meshes, &CardClass::Rogue, &CardType::Spell)?,
-            );
-            res.insert(
-                format!("{:?}_{:?}", CardType::Spell, CardClass::Druid),
-                builder.build_card_frame(textures, &meshes, &CardClass::Druid, &CardType::Spell)?,
-            );
-            res.insert(
-                format!("{:?}_{:?}", CardType::Spell, CardClass::Paladin),
-                builder.build_card_frame(textures, &meshes, &CardClass::Paladin, &CardType::Spell)?,
-            );
-            res.insert(
-                format!("{:?}_{:?}", CardType::Spell, CardClass::Shaman),
-                builder.build_card_frame(textures, &meshes, &CardClass::Shaman, &CardType::Spell)?,
-            );
-        }
-
-        Ok(res)
-    }
-
     fn load_fonts(assets_path: &str) -> Result<HashMap<Fonts, Font>> {
         let shared = UnpackDef::new(
             &[assets_path, "/shared*.unity3d"].join(""),
             vec!["Font".to_string()],
         )?;
-        let fonts = object_hash(&shared);
+        let mut fonts = object_hash(&shared);
+
+        // locale specific fonts come in their own bundles
+        let localized = UnpackDef::new(
+            &[assets_path, "/fonts*.unity3d"].join(""),
+            vec!["Font".to_string()],
+        )?;
+        fonts.extend(object_hash(&localized));
+
+        let mut localized_names = HashMap::new();
+        for locale in LOCALIZED_FONT_LOCALES.iter() {
+            localized_names.insert(
+                format!("{}_{}", FONT_BELWE, locale.code()),
+                Fonts::LocalizedTitle(*locale),
+            );
+            localized_names.insert(
+                format!("{}_{}", FONT_FRANKLINGOTHIC, locale.code()),
+                Fonts::LocalizedBody(*locale),
+            );
+        }
 
         let mut res = HashMap::new();
         for key in fonts.keys() {
@@ -650,6 +658,8 @@ impl Assets {
                 res.insert(Fonts::BlizzardGlobal, font);
             } else if font.object.name == FONT_FRANKLINGOTHIC {
                 res.insert(Fonts::FranklinGothic, font);
+            } else if let Some(localized) = localized_names.get(&font.object.name) {
+                res.insert(*localized, font);
             }
         }
 
@@ -679,6 +689,39 @@ impl Assets {
             "AbilityCardCurvedText".to_string(),
             "ManaGem".to_string(),
         ];
+        // meshes of the other card types, rendering those types fails when they are missing, the
+        // elite dragon is left out instead
+        let optional_meshes = vec![
+            "InHand_Minion_Base_mesh".to_string(),
+            "InHand_Minion_NameBanner_mesh".to_string(),
+            "InHand_Minion_Description_mesh".to_string(),
+            "InHand_Minion_RarityFrame_mesh".to_string(),
+            "InHand_Minion_Portrait_mesh".to_string(),
+            "MinionCardCurvedText".to_string(),
+            "AttackGem".to_string(),
+            "HealthGem".to_string(),
+            "InHand_Weapon_Base_mesh".to_string(),
+            "InHand_Weapon_NameBanner_mesh".to_string(),
+            "InHand_Weapon_Description_mesh".to_string(),
+            "InHand_Weapon_RarityFrame_mesh".to_string(),
+            "InHand_Weapon_Portrait_mesh".to_string(),
+            "WeaponCardCurvedText".to_string(),
+            "WeaponAttackGem".to_string(),
+            "DurabilityGem".to_string(),
+            "InHand_Hero_Base_mesh".to_string(),
+            "InHand_Hero_NameBanner_mesh".to_string(),
+            "InHand_Hero_Description_mesh".to_string(),
+            "InHand_Hero_RarityFrame_mesh".to_string(),
+            "InHand_Hero_Portrait_mesh".to_string(),
+            "HeroCardCurvedText".to_string(),
+            "ArmorGem".to_string(),
+            "HeroPower_Portrait_mesh".to_string(),
+            "HeroPower_Frame_mesh".to_string(),
+            "InHand_Minion_Elite_mesh".to_string(),
+            "InHand_Ability_Elite_mesh".to_string(),
+            "InHand_Weapon_Elite_mesh".to_string(),
+            "InHand_Hero_Elite_mesh".to_string(),
+        ];
 
         let mut res = HashMap::new();
         for keep in meshes_to_keep {
@@ -686,22 +729,18 @@ impl Assets {
             let mesh = engine_object.to_mesh()?;
             res.insert(mesh.object.name.clone(), mesh);
         }
+        for keep in optional_meshes {
+            if meshes.contains_key(&keep) {
+                let mesh = Assets::catalog_get(&meshes, &keep)?.to_mesh()?;
+                res.insert(mesh.object.name.clone(), mesh);
+            }
+        }
 
         Ok(res)
     }
 
-    pub fn get_card_frame(
-        &self,
-        card_type: &CardType,
-        card_class: &CardClass,
-    ) -> Result<&RenderTexture> {
-        let key = format!("{:?}_{:?}", card_type, card_class);
-        Ok(match self.card_frames.get(&key) {
-            Some(k) => k,
-            None => {
-                return Err(Error::AssetNotFoundError(format!("Cannot find {}", key)));
-            }
-        })
+    pub fn has_font(&self, font_name: &Fonts) -> bool {
+        self.fonts.contains_key(font_name)
     }
 
     pub fn get_font(&self, font_name: &Fonts) -> Result<&Font> {
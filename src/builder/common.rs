@@ -2,184 +2,40 @@ use error::{Error, Result};
 use sfml::system::Vector2f;
 use byteorder::{LittleEndian, ReadBytesExt};
 use sfml::graphics::{BlendMode, Color, Image, PrimitiveType, RenderStates, RenderTarget,
-                     RenderTexture, Text, Texture, Transform, Transformable, Vertex, VertexArray};
+                     RenderTexture, Text, Texture, TextureRef, Transform, Transformable, Vertex,
+                     VertexArray};
 use sfml::system::Vector2u;
 use unitypack::engine::mesh::Mesh;
 use std::{usize, f32};
 use std::io::BufReader;
 use builder::TRANSPARENT_COLOR;
 
-pub fn build_portrait(
-    portrait_image: &Image,
-    shadow_image: &Image,
-    mesh: &Mesh,
-) -> Result<RenderTexture> {
-    let portrait_vertex_array = create_vertex_array(
-        mesh,
-        1,
-        0,
-        3,
-        portrait_image.size().x,
-        portrait_image.size().y,
-        284,
-        false,
-        &Vector2u { x: 0, y: 0 },
-    )?;
-
-    let mut portrait_texture = Texture::from_image(&portrait_image).ok_or(Error::SFMLError)?;
-    portrait_texture.set_smooth(true);
-    let mut shadow_texture = Texture::from_image(&shadow_image).ok_or(Error::SFMLError)?;
-    shadow_texture.set_smooth(true);
-
-    // create canvas
-    let portrait_bounds = portrait_vertex_array.bounds();
-    let mut canvas = RenderTexture::new(
-        portrait_bounds.width.ceil() as u32,
-        portrait_bounds.height.ceil() as u32,
-        false,
-    ).ok_or(Error::SFMLError)?;
-    canvas.set_smooth(true);
-    canvas.clear(&TRANSPARENT_COLOR);
-
-    let mut flip_transform = Transform::default();
-    flip_transform.scale_with_center(
-        -1f32,
-        1f32,
-        portrait_bounds.width / 2f32,
-        portrait_bounds.height / 2f32,
-    );
-
-    let render_states = RenderStates::new(
-        BlendMode::default(),
-        flip_transform,
-        Some(&portrait_texture),
-        None,
-    );
-    canvas.draw_with_renderstates(&portrait_vertex_array, render_states);
-
-    // render shadow
-    let shadow_vertex_array = create_vertex_array(
-        mesh,
-        1,
-        0,
-        4,
-        shadow_image.size().x,
-        shadow_image.size().y,
-        284,
-        false,
-        &Vector2u { x: 0, y: 0 },
-    )?;
-
-    let render_states = RenderStates::new(
-        BlendMode::MULTIPLY,
-        flip_transform,
-        Some(&shadow_texture),
-        None,
-    );
-
-    canvas.draw_with_renderstates(&shadow_vertex_array, render_states);
-
-    // do the rendering
-    canvas.display();
-
-    Ok(canvas)
-}
-
-pub fn build_portrait_frame(frame_image: &Image, mesh: &Mesh) -> Result<RenderTexture> {
-    let frame_vertex_array = create_vertex_array(
-        mesh,
-        0,
-        0,
-        3,
-        frame_image.size().x,
-        frame_image.size().y,
-        307,
-        false,
-        &Vector2u { x: 0, y: 0 },
-    )?;
-    let mut frame_image_texture = Texture::from_image(&frame_image).ok_or(Error::SFMLError)?;
-    frame_image_texture.set_smooth(true);
-
-    let bounds = frame_vertex_array.bounds();
-    let mut canvas = RenderTexture::new(
-        bounds.width.ceil() as u32,
-        bounds.height.ceil() as u32,
-        false,
-    ).ok_or(Error::SFMLError)?;
-    canvas.set_smooth(true);
-    canvas.clear(&TRANSPARENT_COLOR);
-
-    let render_states = RenderStates::new(
-        BlendMode::default(),
-        Transform::default(),
-        Some(&frame_image_texture),
-        None,
-    );
-    canvas.draw_with_renderstates(&frame_vertex_array, render_states);
-    canvas.display();
-    Ok(canvas)
-}
-
-pub fn build_ability_name_banner(
-    banner_image: &Image,
+// Projects the texture through a submesh, the offset moves the texture coordinates
+pub fn build_mesh_layer(
+    texture: &TextureRef,
     mesh: &Mesh,
+    submesh_idx: usize,
+    texcoord_channel_idx: usize,
     width: usize,
+    sort_by_z: bool,
+    texture_offset: &Vector2u,
 ) -> Result<RenderTexture> {
-    let frame_vertex_array = create_vertex_array(
-        mesh,
-        0,
-        0,
-        3, // texcoord channel
-        banner_image.size().x,
-        banner_image.size().y,
-        width,
-        true,
-        &Vector2u { x: 0, y: 0 },
-    )?;
-    let mut banner_image_texture = Texture::from_image(&banner_image).ok_or(Error::SFMLError)?;
-    banner_image_texture.set_smooth(true);
-
-    let bounds = frame_vertex_array.bounds();
-    let mut canvas = RenderTexture::new(
-        (bounds.width.ceil()) as u32,
-        (bounds.height.ceil()) as u32,
-        false,
-    ).ok_or(Error::SFMLError)?;
-    canvas.set_smooth(true);
-    canvas.clear(&TRANSPARENT_COLOR);
-
-    let render_states = RenderStates::new(
-        BlendMode::default(),
-        Transform::default(),
-        Some(&banner_image_texture),
-        None,
-    );
-    canvas.draw_with_renderstates(&frame_vertex_array, render_states);
-    canvas.display();
-
-    Ok(canvas)
-}
-
-pub fn build_mana_gem(mana_gem_image: &Image, mesh: &Mesh, width: usize) -> Result<RenderTexture> {
     let vertex_array = create_vertex_array(
         mesh,
+        submesh_idx,
         0,
-        0,
-        3, // texcoord channel
-        mana_gem_image.size().x,
-        mana_gem_image.size().y,
+        texcoord_channel_idx,
+        texture.size().x,
+        texture.size().y,
         width,
-        true,
-        &Vector2u { x: 0, y: 0 },
+        sort_by_z,
+        texture_offset,
     )?;
 
-    let mut mana_gem_texture = Texture::from_image(&mana_gem_image).ok_or(Error::SFMLError)?;
-    mana_gem_texture.set_smooth(true);
-
     let bounds = vertex_array.bounds();
     let mut canvas = RenderTexture::new(
-        (bounds.width.ceil()) as u32,
-        (bounds.height.ceil()) as u32,
+        bounds.width.ceil() as u32,
+        bounds.height.ceil() as u32,
         false,
     ).ok_or(Error::SFMLError)?;
     canvas.set_smooth(true);
@@ -188,7 +44,7 @@ pub fn build_mana_gem(mana_gem_image: &Image, mesh: &Mesh, width: usize) -> Resu
     let render_states = RenderStates::new(
         BlendMode::default(),
         Transform::default(),
-        Some(&mana_gem_texture),
+        Some(texture),
         None,
     );
     canvas.draw_with_renderstates(&vertex_array, render_states);
@@ -197,25 +53,21 @@ pub fn build_mana_gem(mana_gem_image: &Image, mesh: &Mesh, width: usize) -> Resu
     Ok(canvas)
 }
 
-pub fn build_rarity_socket(
-    socket_image: &Image,
-    mesh: &Mesh,
-    width: usize,
-) -> Result<RenderTexture> {
+pub fn build_gem(gem_image: &Image, mesh: &Mesh, width: usize) -> Result<RenderTexture> {
     let vertex_array = create_vertex_array(
         mesh,
         0,
         0,
         3, // texcoord channel
-        socket_image.size().x,
-        socket_image.size().y,
+        gem_image.size().x,
+        gem_image.size().y,
         width,
         true,
         &Vector2u { x: 0, y: 0 },
     )?;
 
-    let mut rarity_socket_texture = Texture::from_image(&socket_image).ok_or(Error::SFMLError)?;
-    rarity_socket_texture.set_smooth(true);
+    let mut gem_texture = Texture::from_image(&gem_image).ok_or(Error::SFMLError)?;
+    gem_texture.set_smooth(true);
 
     let bounds = vertex_array.bounds();
     let mut canvas = RenderTexture::new(
@@ -229,7 +81,7 @@ pub fn build_rarity_socket(
     let render_states = RenderStates::new(
         BlendMode::default(),
         Transform::default(),
-        Some(&rarity_socket_texture),
+        Some(&gem_texture),
         None,
     );
     canvas.draw_with_renderstates(&vertex_array, render_states);
@@ -305,17 +157,26 @@ pub fn build_rarity_gem(
     Ok(canvas)
 }
 
-pub fn build_name_texture(text: &mut Text) -> Result<RenderTexture> {
-    let center = Vector2f::new(150f32, 22f32);
-    let bounds = text.local_bounds();
-
-    text.set_position(Vector2f::new(center.x - (bounds.width / 2f32), 41f32));
-    text.set_scale(Vector2f { x: 1f32, y: -1f32 });
+// Takes the name split into pieces of the same font along with their horizontal advances
+pub fn build_name_texture(segments: &mut Vec<(Text, f32)>, scale: f32) -> Result<RenderTexture> {
+    let center = Vector2f::new(150f32 * scale, 22f32 * scale);
+    let width: f32 = segments.iter().map(|&(_, advance)| advance).sum();
 
-    let mut canvas = RenderTexture::new(300, 44, false).ok_or(Error::SFMLError)?;
+    let mut canvas = RenderTexture::new(
+        (300f32 * scale).ceil() as u32,
+        (44f32 * scale).ceil() as u32,
+        false,
+    ).ok_or(Error::SFMLError)?;
     canvas.set_smooth(true);
     canvas.clear(&TRANSPARENT_COLOR);
-    canvas.draw(text);
+
+    let mut x = center.x - (width / 2f32);
+    for &mut (ref mut text, advance) in segments.iter_mut() {
+        text.set_position(Vector2f::new(x, 41f32 * scale));
+        text.set_scale(Vector2f { x: 1f32, y: -1f32 });
+        canvas.draw(text);
+        x += advance;
+    }
     canvas.display();
 
     Ok(canvas)
@@ -323,6 +184,14 @@ pub fn build_name_texture(text: &mut Text) -> Result<RenderTexture> {
 
 // Utility functions
 // -----------------
+/// Scales a width measured on a CARD_WIDTH wide card, layers never shrink to nothing
+pub fn scale_width(width: usize, scale: f32) -> usize {
+    if width == 0 {
+        return 0;
+    }
+    ((width as f32 * scale).round() as usize).max(1)
+}
+
 struct Vertex3D {
     coord_x: f32,
     coord_y: f32,
@@ -451,11 +320,13 @@ pub fn create_vertex_array(
     sort_by_z: bool,
     texture_offset: &Vector2u,
 ) -> Result<VertexArray> {
+    // the indexes come from layouts, which may be loaded at runtime
     let submesh = mesh.submeshes
         .get(submesh_idx)
-        .ok_or(Error::AssetNotFoundError(format!(
-            "Submesh {} not found",
-            submesh_idx
+        .ok_or(Error::InvalidAssetError(format!(
+            "Submesh {} not found, the mesh has {}",
+            submesh_idx,
+            mesh.submeshes.len()
         )))?;
 
     // size of data per vertex
@@ -463,22 +334,10 @@ pub fn create_vertex_array(
     // vertex data offset of the current submesh
     let data_offset = submesh.first_byte as usize;
 
-    let coord_channel_offset = mesh.vertex_data.channels[coord_channel_idx]
-        .get(&"offset".to_string())
-        .ok_or(Error::ObjectTypeError)?
-        .to_u8()? as usize;
-    let coord_channel_size = mesh.vertex_data.channels[coord_channel_idx]
-        .get(&"dimension".to_string())
-        .ok_or(Error::ObjectTypeError)?
-        .to_u8()? as usize * 4;
-    let texcoord_channel_offset = mesh.vertex_data.channels[texcoord_channel_idx]
-        .get(&"offset".to_string())
-        .ok_or(Error::ObjectTypeError)?
-        .to_u8()? as usize;
-    let texcoord_channel_size = mesh.vertex_data.channels[texcoord_channel_idx]
-        .get(&"dimension".to_string())
-        .ok_or(Error::ObjectTypeError)?
-        .to_u8()? as usize * 4;
+    let coord_channel_offset = channel_attribute(mesh, coord_channel_idx, "offset")?;
+    let coord_channel_size = channel_attribute(mesh, coord_channel_idx, "dimension")? * 4;
+    let texcoord_channel_offset = channel_attribute(mesh, texcoord_channel_idx, "offset")?;
+    let texcoord_channel_size = channel_attribute(mesh, texcoord_channel_idx, "dimension")? * 4;
 
     if submesh.index_count % 3 != 0 {
         return Err(Error::InvalidAssetError(format!(
@@ -543,3 +402,18 @@ pub fn create_vertex_array(
 
     Ok(vertex_array)
 }
+
+fn channel_attribute(mesh: &Mesh, channel_idx: usize, attribute: &str) -> Result<usize> {
+    let channel = mesh.vertex_data
+        .channels
+        .get(channel_idx)
+        .ok_or(Error::InvalidAssetError(format!(
+            "Channel {} not found, the mesh has {}",
+            channel_idx,
+            mesh.vertex_data.channels.len()
+        )))?;
+    Ok(channel
+        .get(&attribute.to_string())
+        .ok_or(Error::ObjectTypeError)?
+        .to_u8()? as usize)
+}
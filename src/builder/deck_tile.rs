@@ -0,0 +1,237 @@
+use builder::{FontChain, TRANSPARENT_COLOR};
+use error::{Error, Result};
+use sfml::graphics::{Color, ConvexShape, FloatRect, Font, Image, IntRect, PrimitiveType,
+                     RenderTarget, RenderTexture, Shape, Sprite, Text, TextStyle, Texture,
+                     Transformable, Vertex, VertexArray};
+use sfml::system::Vector2f;
+use std::f32;
+
+/// Size of the tile all offsets and sizes are measured on
+pub const TILE_WIDTH: usize = 240;
+pub const TILE_HEIGHT: usize = 40;
+
+/// Width of the box with the number of copies on the right side of the tile
+pub const TILE_COUNT_WIDTH: f32 = 30f32;
+
+/// Width of the mana gem on a CARD_WIDTH wide card, the cost on the tile's gem is sized like
+/// the cost on a card gem of the same size
+pub const TILE_CARD_GEM_WIDTH: f32 = 94f32;
+
+// the portrait strip starts after the beginning of the name
+const PORTRAIT_LEFT: f32 = 100f32;
+
+lazy_static! {
+    static ref BACKGROUND_COLOR: Color = {
+        Color::rgb(32, 32, 32)
+    };
+    static ref COUNT_COLOR: Color = {
+        Color::rgb(255, 209, 0)
+    };
+}
+
+/// Dark bar with a strip of the portrait on its right side, the strip fades into the bar
+pub fn build_tile_background(
+    portrait_image: &Image,
+    with_count: bool,
+    width: u32,
+    height: u32,
+) -> Result<RenderTexture> {
+    let scale = width as f32 / TILE_WIDTH as f32;
+    let mut canvas = RenderTexture::new(width, height, false).ok_or(Error::SFMLError)?;
+    canvas.set_smooth(true);
+    canvas.clear(&BACKGROUND_COLOR);
+
+    let strip_left = PORTRAIT_LEFT * scale;
+    let strip_right = if with_count {
+        width as f32 - TILE_COUNT_WIDTH * scale
+    } else {
+        width as f32
+    };
+    let strip_width = strip_right - strip_left;
+
+    // the strip is cut from the upper middle of the portrait where faces usually are, portrait
+    // textures are stored bottom row first so the rect is flipped
+    let portrait_size = portrait_image.size();
+    let crop_height = ((portrait_size.x as f32 * height as f32 / strip_width) as u32)
+        .min(portrait_size.y)
+        .max(1);
+    let crop_bottom = ((portrait_size.y as f32 * 0.65f32) as u32 + crop_height / 2)
+        .min(portrait_size.y)
+        .max(crop_height);
+
+    let mut texture = Texture::from_image(portrait_image).ok_or(Error::SFMLError)?;
+    texture.set_smooth(true);
+    let mut sprite = Sprite::with_texture(&texture);
+    sprite.set_texture_rect(&IntRect::new(
+        0,
+        crop_bottom as i32,
+        portrait_size.x as i32,
+        -(crop_height as i32),
+    ));
+    sprite.set_scale(Vector2f::new(
+        strip_width / portrait_size.x as f32,
+        height as f32 / crop_height as f32,
+    ));
+    sprite.set_position(Vector2f::new(strip_left, 0f32));
+    canvas.draw(&sprite);
+
+    let fade_right = strip_left + strip_width / 2f32;
+    let faded = Color::rgba(BACKGROUND_COLOR.r, BACKGROUND_COLOR.g, BACKGROUND_COLOR.b, 0);
+    let mut fade = VertexArray::new(PrimitiveType::Quads, 0);
+    for &(x, y, ref color) in [
+        (strip_left, 0f32, *BACKGROUND_COLOR),
+        (fade_right, 0f32, faded),
+        (fade_right, height as f32, faded),
+        (strip_left, height as f32, *BACKGROUND_COLOR),
+    ].iter()
+    {
+        fade.append(&Vertex::new(
+            Vector2f::new(x, y),
+            *color,
+            Vector2f::new(0f32, 0f32),
+        ));
+    }
+    canvas.draw(&fade);
+    canvas.display();
+
+    Ok(canvas)
+}
+
+/// Box with the number of copies like "x2", or a star for a single legendary card
+pub fn build_tile_count(
+    copies: u32,
+    font: &Font,
+    pixel_scale: f32,
+    width: u32,
+    height: u32,
+) -> Result<RenderTexture> {
+    let scale = width as f32 / TILE_WIDTH as f32;
+    let box_width = (TILE_COUNT_WIDTH * scale).round() as u32;
+    let mut canvas = RenderTexture::new(box_width, height, false).ok_or(Error::SFMLError)?;
+    canvas.set_smooth(true);
+    canvas.clear(&Color::rgb(16, 16, 16));
+    let center = Vector2f::new(box_width as f32 / 2f32, height as f32 / 2f32);
+
+    if copies > 1 {
+        let character_size = ((18f32 * scale).round() as u32).max(1);
+        let mut text = Text::new(&format!("x{}", copies), font, character_size);
+        text.set_style(TextStyle::BOLD);
+        text.set_fill_color(&COUNT_COLOR);
+        text.scale(Vector2f::new(1f32 + pixel_scale, 1f32 + pixel_scale));
+        let bounds = text.global_bounds();
+        text.set_position(Vector2f::new(
+            center.x - bounds.width / 2f32 - bounds.left,
+            center.y - bounds.height / 2f32 - bounds.top,
+        ));
+        canvas.draw(&text);
+    } else {
+        // five pointed star, the points alternate between the outer and the inner radius
+        let outer_radius = 9f32 * scale;
+        let inner_radius = outer_radius * 0.4f32;
+        let mut star = ConvexShape::new(10);
+        for point in 0..10 {
+            let radius = if point % 2 == 0 {
+                outer_radius
+            } else {
+                inner_radius
+            };
+            let angle = point as f32 * f32::consts::PI / 5f32 - f32::consts::FRAC_PI_2;
+            star.set_point(
+                point,
+                Vector2f::new(
+                    center.x + radius * angle.cos(),
+                    center.y + radius * angle.sin(),
+                ),
+            );
+        }
+        star.set_fill_color(&COUNT_COLOR);
+        canvas.draw(&star);
+    }
+    canvas.display();
+
+    Ok(canvas)
+}
+
+/// Name of the card, upright and left aligned on a canvas as large as the text
+pub fn build_tile_name(card_name: &str, fonts: &FontChain, width: u32) -> Result<RenderTexture> {
+    let scale = width as f32 / TILE_WIDTH as f32;
+    let character_size = ((17f32 * scale).round() as u32).max(1);
+
+    // the pieces of the name are placed one after the other, the canvas covers all of them
+    let mut texts = Vec::new();
+    let mut bounds: Option<FloatRect> = None;
+    let mut x = 0f32;
+    for (font, segment) in fonts.segments(card_name) {
+        let mut text = Text::new(&segment, font, character_size);
+        text.set_outline_color(&Color::BLACK);
+        text.set_outline_thickness(scale);
+        text.set_position(Vector2f::new(x, 0f32));
+        x = text.find_character_pos(segment.chars().count()).x;
+        let text_bounds = text.global_bounds();
+        bounds = Some(match bounds {
+            Some(bounds) => {
+                let left = bounds.left.min(text_bounds.left);
+                let top = bounds.top.min(text_bounds.top);
+                FloatRect::new(
+                    left,
+                    top,
+                    (bounds.left + bounds.width).max(text_bounds.left + text_bounds.width) - left,
+                    (bounds.top + bounds.height).max(text_bounds.top + text_bounds.height) - top,
+                )
+            }
+            None => text_bounds,
+        });
+        texts.push(text);
+    }
+
+    let bounds = bounds.unwrap_or(FloatRect::new(0f32, 0f32, 1f32, 1f32));
+    draw_in_bounds(&bounds, |canvas, offset| {
+        for text in &mut texts {
+            let position = text.position();
+            text.set_position(position + offset);
+            canvas.draw(text);
+        }
+    })
+}
+
+// Canvas as large as the bounds, the offset moves the top left corner of the bounds to the
+// corner of the canvas
+fn draw_in_bounds<F>(bounds: &FloatRect, draw: F) -> Result<RenderTexture>
+where
+    F: FnOnce(&mut RenderTexture, Vector2f),
+{
+    let mut canvas = RenderTexture::new(
+        (bounds.width.ceil() as u32).max(1),
+        (bounds.height.ceil() as u32).max(1),
+        false,
+    ).ok_or(Error::SFMLError)?;
+    canvas.set_smooth(true);
+    canvas.clear(&TRANSPARENT_COLOR);
+    draw(&mut canvas, Vector2f::new(-bounds.left, -bounds.top));
+    canvas.display();
+
+    Ok(canvas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sfml::graphics::RectangleShape;
+
+    #[test]
+    fn draw_upright_in_bounds() {
+        let bounds = FloatRect::new(10f32, 20f32, 8f32, 6f32);
+        let canvas = draw_in_bounds(&bounds, |canvas, offset| {
+            // only the upper half of the bounds is filled
+            let mut rectangle = RectangleShape::with_size(Vector2f::new(8f32, 3f32));
+            rectangle.set_fill_color(&Color::rgb(255, 255, 255));
+            rectangle.set_position(Vector2f::new(10f32, 20f32) + offset);
+            canvas.draw(&rectangle);
+        }).unwrap();
+
+        let image = canvas.texture().copy_to_image().unwrap();
+        assert_eq!((image.size().x, image.size().y), (8, 6));
+        assert_eq!(image.pixel_at(4, 1).a, 255);
+        assert_eq!(image.pixel_at(4, 4).a, 0);
+    }
+}
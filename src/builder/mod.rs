@@ -1,15 +1,23 @@
 mod common;
-mod ability;
+mod deck_tile;
+mod text;
+
+pub use self::common::scale_width;
+pub use self::deck_tile::{build_tile_background, build_tile_count, build_tile_name,
+                          TILE_CARD_GEM_WIDTH, TILE_COUNT_WIDTH, TILE_HEIGHT, TILE_WIDTH};
+pub use self::text::FontChain;
 
 use error::{Error, Result};
 use std::collections::HashMap;
-use sfml::graphics::{Color, Image, RenderTexture, Shader, Text, TextStyle, TextureRef};
+use sfml::graphics::{Color, Image, RenderTexture, Text, TextStyle, Texture, TextureRef};
 use sfml::system::Vector2u;
-use utils::ImageUtils;
+use utils::{ImageUtils, IntoImage};
 use unitypack::engine::mesh::Mesh;
 use unitypack::engine::texture::IntoTexture2D;
-use cards::{CardClass, CardRarity, CardType};
+use cards::{CardRarity, Premium};
+use layout::Layer;
 use assets::Assets;
+use markup::CardText;
 
 //const VERTEX_SHADER_SOURCE: &'static str = include_str!("../../res/vertex_shader.glsl");
 //const FRAGMENT_SHADER_SOURCE: &'static str = include_str!("../../res/fragment_shader.glsl");
@@ -20,155 +28,153 @@ lazy_static! {
     };
 }
 
-pub struct Builder<'a> {
-    shader: Option<Shader<'a>>,
-}
+/// Width of the card frame all offsets and sizes are measured on
+pub const CARD_WIDTH: usize = 360;
 
-impl<'a> Builder<'a> {
-    pub fn new() -> Result<Builder<'a>> {
-        Ok(Builder {
-            shader: None, /*Shader::from_memory(
-                // do not use the shader as it is currently broken
-                Some(VERTEX_SHADER_SOURCE),
-                None,
-                Some(FRAGMENT_SHADER_SOURCE),
-            )*/
-        })
-    }
+/// Narrowest card that is rendered, smaller cards lose their text
+pub const MIN_CARD_WIDTH: usize = 64;
 
-    pub fn build_card_frame(
-        &self,
-        texture_map: &HashMap<String, String>,
-        meshes_map: &HashMap<String, Mesh>,
-        card_class: &CardClass,
-        card_type: &CardType,
-    ) -> Result<RenderTexture> {
-        match *card_type {
-            CardType::Spell | CardType::Enchantment => ability::build_ability_frame_for_class(
-                texture_map,
-                meshes_map,
-                self.shader.as_ref(),
-                card_class,
-            ),
-            _ => Err(Error::NotImplementedError(format!(
-                "Card type {:?} is not implemented",
-                card_type
-            ))),
-        }
-    }
-}
-
-pub fn build_ability_portrait(
-    portrait_image: &Image,
+/// Projects a texture of the asset catalog through the mesh of the layer
+pub fn build_layer(
     texture_map: &HashMap<String, String>,
     meshes_map: &HashMap<String, Mesh>,
+    layer: &Layer,
+    texture_name: &str,
+    width: usize,
 ) -> Result<RenderTexture> {
-    let mesh = meshes_map
-        .get(&"InHand_Ability_Portrait_mesh".to_string())
-        .ok_or(Error::AssetNotFoundError(format!(
-            "InHand_Ability_Portrait_mesh is not found in meshes"
-        )))?;
-
-    let shadow_texture =
-        Assets::catalog_get(&texture_map, "Card_InHand_BannerAtlas")?.to_texture2d()?;
-    let shadow_image = Image::create_from_pixels(
-        shadow_texture.width,
-        shadow_texture.height,
-        &shadow_texture.to_image()?,
-    ).ok_or(Error::SFMLError)?;
-    common::build_portrait(portrait_image, &shadow_image, mesh)
+    let mut image = Assets::catalog_get(texture_map, texture_name)?
+        .to_texture2d()?
+        .to_sfml_image()?;
+    if layer.opaque {
+        image.remove_transparency();
+    }
+    build_image_layer(&image, meshes_map, layer, width)
 }
 
-// Returned texture needs to be flipped vertically
-pub fn build_ability_portrait_frame(
-    frame_image: &Image,
+/// Projects the image through the mesh of the layer, used for portraits
+pub fn build_image_layer(
+    image: &Image,
     meshes_map: &HashMap<String, Mesh>,
+    layer: &Layer,
+    width: usize,
 ) -> Result<RenderTexture> {
-    let mesh = meshes_map
-        .get(&"InHand_Ability_Portrait_mesh".to_string())
-        .ok_or(Error::AssetNotFoundError(format!(
-            "InHand_Ability_Portrait_mesh is not found in meshes"
-        )))?;
-
-    common::build_portrait_frame(frame_image, mesh)
+    let mesh = layer_mesh(meshes_map, layer)?;
+    let mut texture = Texture::from_image(image).ok_or(Error::SFMLError)?;
+    texture.set_smooth(true);
+
+    common::build_mesh_layer(
+        &texture,
+        mesh,
+        layer.submesh,
+        layer.channel,
+        width,
+        layer.sort_by_z,
+        &Vector2u { x: 0, y: 0 },
+    )
 }
 
-// Returned texture needs to be flipped vertically
-pub fn build_ability_name_banner(
+/// Effect layer of golden portraits at the given phase of the animation, between 0 and 1.
+/// The returned texture needs to be flipped vertically, like the portrait it is drawn over.
+pub fn build_portrait_effect(
     texture_map: &HashMap<String, String>,
     meshes_map: &HashMap<String, Mesh>,
+    layer: &Layer,
+    texture_name: &str,
+    phase: f32,
     width: usize,
 ) -> Result<RenderTexture> {
-    let banner_source =
-        Assets::catalog_get(texture_map, "Card_InHand_BannerAtlas")?.to_texture2d()?;
+    let mesh = layer_mesh(meshes_map, layer)?;
+    let effect_image = Assets::catalog_get(texture_map, texture_name)?
+        .to_texture2d()?
+        .to_sfml_image()?;
+
+    // the offset moves the coordinates past the edge of the texture
+    let mut effect_texture = Texture::from_image(&effect_image).ok_or(Error::SFMLError)?;
+    effect_texture.set_smooth(true);
+    effect_texture.set_repeated(true);
+
+    // the effect scrolls diagonally, one full phase moves it by the texture size
+    let texture_offset = Vector2u {
+        x: (phase * effect_image.size().x as f32) as u32,
+        y: (phase * effect_image.size().y as f32) as u32,
+    };
+    common::build_mesh_layer(
+        &effect_texture,
+        mesh,
+        layer.submesh,
+        layer.channel,
+        width,
+        layer.sort_by_z,
+        &texture_offset,
+    )
+}
 
-    let mesh = meshes_map
-        .get(&"InHand_Ability_NameBanner_mesh".to_string())
+fn layer_mesh<'a>(meshes_map: &'a HashMap<String, Mesh>, layer: &Layer) -> Result<&'a Mesh> {
+    let mesh_name = layer.mesh.as_ref().ok_or(Error::InvalidAssetError(format!(
+        "Layer {} has no mesh",
+        layer.name
+    )))?;
+    meshes_map
+        .get(mesh_name)
         .ok_or(Error::AssetNotFoundError(format!(
-            "InHand_Ability_NameBanner_mesh is not found in meshes"
-        )))?;
-
-    let banner_image = Image::create_from_pixels(
-        banner_source.width,
-        banner_source.height,
-        &banner_source.to_image()?,
-    ).ok_or(Error::SFMLError)?;
-
-    common::build_ability_name_banner(&banner_image, mesh, width)
+            "{} is not found in meshes",
+            mesh_name
+        )))
 }
 
 pub fn build_mana_gem(
     texture_map: &HashMap<String, String>,
     meshes_map: &HashMap<String, Mesh>,
+    premium: Premium,
     width: usize,
 ) -> Result<RenderTexture> {
-    let mana_gem_texture = Assets::catalog_get(texture_map, "Gem_Mana_D")?.to_texture2d()?;
-
-    let mana_gem_mesh = meshes_map
-        .get("ManaGem")
-        .ok_or(Error::AssetNotFoundError(format!("Cannot find ManaGem")))?;
-
-    let mut mana_gem_image = Image::create_from_pixels(
-        mana_gem_texture.width,
-        mana_gem_texture.height,
-        &mana_gem_texture.to_image()?,
-    ).ok_or(Error::SFMLError)?;
-    mana_gem_image.remove_transparency();
-
-    common::build_mana_gem(&mana_gem_image, mana_gem_mesh, width)
+    build_gem(
+        texture_map,
+        meshes_map,
+        &premium.texture_name("Gem_Mana_D"),
+        "ManaGem",
+        width,
+    )
 }
 
-pub fn build_rarity_gem_socket(
+fn build_gem(
     texture_map: &HashMap<String, String>,
     meshes_map: &HashMap<String, Mesh>,
+    texture_name: &str,
+    mesh_name: &str,
     width: usize,
 ) -> Result<RenderTexture> {
-    let texture = Assets::catalog_get(texture_map, "Card_Inhand_Ability_Warlock")?.to_texture2d()?;
-    let mesh = meshes_map.get("InHand_Ability_RarityFrame_mesh").ok_or(
-        Error::AssetNotFoundError(format!("Cannot find InHand_Ability_RarityFrame_mesh")),
-    )?;
+    let gem_texture = Assets::catalog_get(texture_map, texture_name)?.to_texture2d()?;
 
-    let gem_socket_image =
-        Image::create_from_pixels(texture.width, texture.height, &texture.to_image()?)
-            .ok_or(Error::SFMLError)?;
+    let gem_mesh = meshes_map
+        .get(mesh_name)
+        .ok_or(Error::AssetNotFoundError(format!("Cannot find {}", mesh_name)))?;
 
-    common::build_rarity_socket(&gem_socket_image, mesh, width)
+    let mut gem_image = Image::create_from_pixels(
+        gem_texture.width,
+        gem_texture.height,
+        &gem_texture.to_image()?,
+    ).ok_or(Error::SFMLError)?;
+    gem_image.remove_transparency();
+
+    common::build_gem(&gem_image, gem_mesh, width)
 }
 
 pub fn build_rarity_gem(
     texture_map: &HashMap<String, String>,
     meshes_map: &HashMap<String, Mesh>,
+    layer: &Layer,
     rarity: &CardRarity,
     width: usize,
 ) -> Result<RenderTexture> {
-    let gem_texture = Assets::catalog_get(texture_map, "RarityGems")?.to_texture2d()?;
+    let texture_name = layer.texture.as_ref().ok_or(Error::InvalidAssetError(format!(
+        "Layer {} has no texture",
+        layer.name
+    )))?;
+    let gem_texture = Assets::catalog_get(texture_map, texture_name)?.to_texture2d()?;
     let shader_texture = Assets::catalog_get(texture_map, "clouds3")?.to_texture2d()?;
 
-    let mesh = meshes_map
-        .get("RarityGem_mesh")
-        .ok_or(Error::AssetNotFoundError(format!(
-            "Cannot find RarityGem_mesh"
-        )))?;
+    let mesh = layer_mesh(meshes_map, layer)?;
 
     let mut gem_image = Image::create_from_pixels(
         gem_texture.width,
@@ -218,24 +224,60 @@ pub fn build_rarity_gem(
     common::build_rarity_gem(&gem_image, &shader_image, mesh, &offset, width)
 }
 
+/// Curves the rendered name along the mesh of the layer
 pub fn build_card_name(
     name_texture: &TextureRef,
     meshes_map: &HashMap<String, Mesh>,
+    layer: &Layer,
     width: usize,
 ) -> Result<RenderTexture> {
-    let mesh = meshes_map
-        .get("AbilityCardCurvedText")
-        .ok_or(Error::AssetNotFoundError(format!(
-            "Cannot find AbilityCardCurvedText"
-        )))?;
+    let mesh = layer_mesh(meshes_map, layer)?;
+    common::build_mesh_layer(
+        name_texture,
+        mesh,
+        layer.submesh,
+        layer.channel,
+        width,
+        layer.sort_by_z,
+        &Vector2u { x: 0, y: 0 },
+    )
+}
 
-    ability::build_card_name(name_texture, mesh, width)
+pub fn build_name_texture(card_name: &str, fonts: &FontChain, scale: f32) -> Result<RenderTexture> {
+    let mut segments: Vec<(Text, f32)> = fonts
+        .segments(card_name)
+        .into_iter()
+        .map(|(font, segment)| {
+            let character_size = ((30f32 * scale).round() as u32).max(1);
+            let mut text = Text::new(&segment, font, character_size);
+            text.set_style(TextStyle::REGULAR);
+            text.set_outline_color(&Color::BLACK);
+            text.set_outline_thickness(2f32 * scale);
+            let advance = text.find_character_pos(segment.chars().count()).x;
+            (text, advance)
+        })
+        .collect();
+    common::build_name_texture(&mut segments, scale)
 }
 
-pub fn build_name_texture(card_name: &str, text: &mut Text) -> Result<RenderTexture> {
-    text.set_string(card_name);
-    text.set_style(TextStyle::REGULAR);
-    text.set_character_size(30);
-    text.set_outline_thickness(2f32);
-    common::build_name_texture(text)
+/// Wraps and shrinks the card text until it fits into a box of the given size
+pub fn build_card_text(
+    card_text: &CardText,
+    fonts: &FontChain,
+    color: &Color,
+    width: u32,
+    height: u32,
+    scale: f32,
+) -> Result<RenderTexture> {
+    let max_character_size = ((30f32 * scale).round() as u32).max(1);
+    let min_character_size = ((12f32 * scale).round() as u32).max(1);
+    let layout = text::layout_text(
+        card_text,
+        fonts,
+        width as f32,
+        height as f32,
+        max_character_size,
+        min_character_size,
+    );
+    text::build_text_texture(&layout, fonts, color, width, height)
 }
@@ -0,0 +1,394 @@
+use assets::Fonts;
+use error::{Error, Result};
+use markup::{CardText, TextRun};
+use sfml::graphics::{Color, Font, RenderTarget, RenderTexture, Text, TextStyle, Transformable};
+use sfml::system::Vector2f;
+use builder::TRANSPARENT_COLOR;
+
+/// A word made of one or more differently styled fragments, e.g. `<b>Taunt</b>.`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Word {
+    pub runs: Vec<TextRun>,
+    /// Words of scripts written without spaces follow the previous word directly
+    pub spaced: bool,
+}
+
+impl Word {
+    fn new(spaced: bool) -> Self {
+        Word {
+            runs: Vec::new(),
+            spaced: spaced,
+        }
+    }
+}
+
+/// Fonts tried in order for every character, the first one covering it is used
+pub struct FontChain<'a> {
+    fonts: Vec<(&'a Font, Fonts)>,
+}
+
+impl<'a> FontChain<'a> {
+    pub fn new(fonts: Vec<(&'a Font, Fonts)>) -> Result<Self> {
+        if fonts.is_empty() {
+            return Err(Error::InternalError);
+        }
+        Ok(FontChain { fonts: fonts })
+    }
+
+    pub fn primary(&self) -> &'a Font {
+        self.fonts[0].0
+    }
+
+    fn font_for(&self, c: char) -> &'a Font {
+        self.fonts
+            .iter()
+            .find(|&&(_, ref kind)| kind.covers(c))
+            .unwrap_or(&self.fonts[0])
+            .0
+    }
+
+    /// Splits the text into pieces that can be drawn with a single font
+    pub fn segments(&self, text: &str) -> Vec<(&'a Font, String)> {
+        let mut segments: Vec<(&'a Font, String)> = Vec::new();
+        for c in text.chars() {
+            let font = self.font_for(c);
+            let same_font = match segments.last() {
+                Some(&(last_font, _)) => last_font as *const Font == font as *const Font,
+                None => false,
+            };
+            if same_font {
+                segments.last_mut().unwrap().1.push(c);
+            } else {
+                segments.push((font, c.to_string()));
+            }
+        }
+        segments
+    }
+}
+
+/// Result of fitting a text into a box: the wrapped lines and the font size they fit with
+pub struct TextLayout {
+    pub lines: Vec<Vec<Word>>,
+    pub character_size: u32,
+    pub line_spacing: f32,
+}
+
+impl TextLayout {
+    pub fn height(&self) -> f32 {
+        self.lines.len() as f32 * self.line_spacing
+    }
+}
+
+/// Wraps the text into the given box, shrinking the font until every line fits
+pub fn layout_text(
+    text: &CardText,
+    fonts: &FontChain,
+    max_width: f32,
+    max_height: f32,
+    max_character_size: u32,
+    min_character_size: u32,
+) -> TextLayout {
+    let paragraphs = split_paragraphs(text);
+    let mut character_size = max_character_size;
+    loop {
+        let layout = wrap_text(
+            &paragraphs,
+            fonts,
+            character_size,
+            max_width,
+            !text.manual_breaks,
+        );
+        if (layout.height() <= max_height && fits_width(&layout, fonts, max_width))
+            || character_size <= min_character_size
+        {
+            return layout;
+        }
+        character_size -= 1;
+    }
+}
+
+// Splits the runs into paragraphs of words, words end at spaces and wherever a line may be
+// broken in scripts without spaces
+fn split_paragraphs(text: &CardText) -> Vec<Vec<Word>> {
+    let mut paragraphs = Vec::new();
+    let mut words: Vec<Word> = Vec::new();
+    let mut word = Word::new(true);
+    // last character of the current word
+    let mut previous: Option<char> = None;
+
+    for run in &text.runs {
+        let mut fragment = String::new();
+        for c in run.text.chars() {
+            if c == '\n' || is_break_space(c) {
+                if !fragment.is_empty() {
+                    word.runs.push(styled(&fragment, run));
+                    fragment.clear();
+                }
+                if !word.runs.is_empty() {
+                    words.push(word);
+                }
+                word = Word::new(true);
+                previous = None;
+                if c == '\n' {
+                    paragraphs.push(words);
+                    words = Vec::new();
+                }
+                continue;
+            }
+            // zero width spaces mark the word boundaries of Thai text, they are not drawn
+            let zero_width_space = c == '\u{200B}';
+            if zero_width_space || previous.map_or(false, |previous| breaks_between(previous, c)) {
+                if !fragment.is_empty() {
+                    word.runs.push(styled(&fragment, run));
+                    fragment.clear();
+                }
+                // a zero width space right after a space keeps the word spaced
+                if !word.runs.is_empty() {
+                    words.push(word);
+                    word = Word::new(false);
+                }
+            }
+            if zero_width_space {
+                previous = None;
+                continue;
+            }
+            fragment.push(c);
+            previous = Some(c);
+        }
+        if !fragment.is_empty() {
+            word.runs.push(styled(&fragment, run));
+        }
+    }
+    if !word.runs.is_empty() {
+        words.push(word);
+    }
+    paragraphs.push(words);
+    paragraphs
+}
+
+// no-break spaces keep numbers and keywords together with the following word
+fn is_break_space(c: char) -> bool {
+    c.is_whitespace() && c != '\u{00A0}' && c != '\u{2007}' && c != '\u{202F}'
+}
+
+// Lines may be broken between CJK ideographs and kana, except before closing and after opening
+// punctuation. Thai words are not separated by spaces, they are only broken at zero width spaces.
+fn breaks_between(previous: char, next: char) -> bool {
+    if is_cjk(previous) || is_cjk(next) {
+        !is_cjk_closing(next) && !is_cjk_opening(previous)
+    } else {
+        false
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    let code = c as u32;
+    // punctuation, kana, ideographs and fullwidth forms
+    (code >= 0x3000 && code <= 0x30FF) || (code >= 0x3400 && code <= 0x4DBF)
+        || (code >= 0x4E00 && code <= 0x9FFF) || (code >= 0xF900 && code <= 0xFAFF)
+        || (code >= 0xFF00 && code <= 0xFF60)
+}
+
+fn is_cjk_closing(c: char) -> bool {
+    "、。，．：；！？）」』】〕〉》ーぁぃぅぇぉっゃゅょァィゥェォッャュョ々…".contains(c)
+}
+
+fn is_cjk_opening(c: char) -> bool {
+    "（「『【〔〈《".contains(c)
+}
+
+fn styled(text: &str, style: &TextRun) -> TextRun {
+    TextRun {
+        text: text.to_string(),
+        bold: style.bold,
+        italic: style.italic,
+    }
+}
+
+fn wrap_text(
+    paragraphs: &Vec<Vec<Word>>,
+    fonts: &FontChain,
+    character_size: u32,
+    max_width: f32,
+    wrap: bool,
+) -> TextLayout {
+    let space_width = measure_advance(" ", fonts, character_size, TextStyle::REGULAR);
+    let mut lines = Vec::new();
+    for paragraph in paragraphs {
+        let mut line: Vec<Word> = Vec::new();
+        let mut line_width = 0f32;
+        for word in paragraph {
+            let word_width = measure_word(word, fonts, character_size);
+            let gap = if word.spaced { space_width } else { 0f32 };
+            if wrap && !line.is_empty() && line_width + gap + word_width > max_width {
+                lines.push(line);
+                line = Vec::new();
+                line_width = 0f32;
+            }
+            if !line.is_empty() {
+                line_width += gap;
+            }
+            line_width += word_width;
+            line.push(word.clone());
+        }
+        lines.push(line);
+    }
+
+    TextLayout {
+        lines: lines,
+        character_size: character_size,
+        line_spacing: fonts.primary().line_spacing(character_size),
+    }
+}
+
+// a single word or a manually broken line can still be wider than the box
+fn fits_width(layout: &TextLayout, fonts: &FontChain, max_width: f32) -> bool {
+    layout
+        .lines
+        .iter()
+        .all(|line| measure_line(line, fonts, layout.character_size) <= max_width)
+}
+
+fn measure_line(line: &Vec<Word>, fonts: &FontChain, character_size: u32) -> f32 {
+    let space_width = measure_advance(" ", fonts, character_size, TextStyle::REGULAR);
+    let mut width = 0f32;
+    for (i, word) in line.iter().enumerate() {
+        if i > 0 && word.spaced {
+            width += space_width;
+        }
+        width += measure_word(word, fonts, character_size);
+    }
+    width
+}
+
+fn measure_word(word: &Word, fonts: &FontChain, character_size: u32) -> f32 {
+    word.runs
+        .iter()
+        .map(|run| measure_advance(&run.text, fonts, character_size, text_style(run)))
+        .sum()
+}
+
+// horizontal distance to the next character after the string
+fn measure_advance(text: &str, fonts: &FontChain, character_size: u32, style: TextStyle) -> f32 {
+    fonts
+        .segments(text)
+        .iter()
+        .map(|&(font, ref segment)| {
+            let mut sf_text = Text::new(segment, font, character_size);
+            sf_text.set_style(style);
+            sf_text.find_character_pos(segment.chars().count()).x
+        })
+        .sum()
+}
+
+fn text_style(run: &TextRun) -> TextStyle {
+    let mut style = TextStyle::REGULAR;
+    if run.bold {
+        style |= TextStyle::BOLD;
+    }
+    if run.italic {
+        style |= TextStyle::ITALIC;
+    }
+    style
+}
+
+/// Renders the lines centered horizontally and vertically into a texture of the given size
+pub fn build_text_texture(
+    layout: &TextLayout,
+    fonts: &FontChain,
+    color: &Color,
+    width: u32,
+    height: u32,
+) -> Result<RenderTexture> {
+    let mut canvas = RenderTexture::new(width, height, false).ok_or(Error::SFMLError)?;
+    canvas.set_smooth(true);
+    canvas.clear(&TRANSPARENT_COLOR);
+
+    let space_width = measure_advance(" ", fonts, layout.character_size, TextStyle::REGULAR);
+    let top = (height as f32 - layout.height()) / 2f32;
+
+    for (i, line) in layout.lines.iter().enumerate() {
+        let line_width = measure_line(line, fonts, layout.character_size);
+        let mut x = (width as f32 - line_width) / 2f32;
+        let y = (top + i as f32 * layout.line_spacing).round();
+        for (j, word) in line.iter().enumerate() {
+            if j > 0 && word.spaced {
+                x += space_width;
+            }
+            for run in &word.runs {
+                for (font, segment) in fonts.segments(&run.text) {
+                    let mut text = Text::new(&segment, font, layout.character_size);
+                    text.set_fill_color(color);
+                    text.set_style(text_style(run));
+                    // measured before positioning, the position is added to the result
+                    let advance = text.find_character_pos(segment.chars().count()).x;
+                    text.set_position(Vector2f::new(x.round(), y));
+                    canvas.draw(&text);
+                    x += advance;
+                }
+            }
+        }
+    }
+
+    canvas.display();
+    Ok(canvas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &str) -> Vec<(String, bool)> {
+        let card_text = CardText {
+            runs: vec![
+                TextRun {
+                    text: text.to_string(),
+                    bold: false,
+                    italic: false,
+                },
+            ],
+            manual_breaks: false,
+        };
+        split_paragraphs(&card_text)[0]
+            .iter()
+            .map(|word| (word.runs[0].text.clone(), word.spaced))
+            .collect()
+    }
+
+    #[test]
+    fn split_on_spaces_but_not_no_break_spaces() {
+        assert_eq!(
+            words("Deal 6\u{00A0}damage. Draw"),
+            vec![
+                ("Deal".to_string(), true),
+                ("6\u{00A0}damage.".to_string(), true),
+                ("Draw".to_string(), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn break_between_ideographs() {
+        let split = words("造成6点伤害。");
+        let texts: Vec<&str> = split.iter().map(|&(ref text, _)| text.as_str()).collect();
+        assert_eq!(texts, vec!["造", "成", "6", "点", "伤", "害。"]);
+        assert!(split[1..].iter().all(|&(_, spaced)| !spaced));
+    }
+
+    #[test]
+    fn break_thai_at_word_boundaries() {
+        let split = words("เล่นไพ่");
+        let texts: Vec<&str> = split.iter().map(|&(ref text, _)| text.as_str()).collect();
+        assert_eq!(texts, vec!["เล่นไพ่"]);
+
+        let split = words("เล่น\u{200B}ไพ่ เล่น");
+        assert_eq!(
+            split,
+            vec![
+                ("เล่น".to_string(), true),
+                ("ไพ่".to_string(), false),
+                ("เล่น".to_string(), true),
+            ]
+        );
+    }
+}
@@ -1,19 +1,30 @@
 use error;
+use serde::{Deserialize, Deserializer};
 use serde_json;
 use std::collections::HashMap;
+use std::collections::hash_map::{Iter, Values};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::ops::{Bound, RangeBounds};
+use std::path::Path;
+use std::str::FromStr;
 
 const CARDDEF_DATA: &'static [u8] = include_bytes!("../res/cards.json");
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug)]
 pub struct CardDb {
-    pub cards: HashMap<String, Card>,
+    cards: HashMap<String, Card>,
+    // ids of the cards by dbfId and by normalized name in every locale
+    dbf_ids: HashMap<u32, String>,
+    names: HashMap<String, Vec<String>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct Card {
     #[serde(rename = "cardClass")] pub card_class: Option<CardClass>,
     pub id: String,
     pub name: Option<Name>,
+    pub text: Option<Name>,
     #[serde(rename = "playerClass")] pub player_class: Option<CardClass>,
     pub rarity: Option<CardRarity>,
     pub set: Option<CardSet>,
@@ -21,6 +32,25 @@ pub struct Card {
     pub cost: Option<i32>,
     #[serde(rename = "type")] pub card_type: Option<CardType>,
     #[serde(rename = "hideStats")] pub hide_stats: Option<bool>,
+    pub attack: Option<i32>,
+    pub health: Option<i32>,
+    pub durability: Option<i32>,
+    pub armor: Option<i32>,
+    #[serde(default)] pub elite: bool,
+    #[serde(rename = "dbfId")] pub dbf_id: Option<u32>,
+    pub flavor: Option<Name>,
+    pub artist: Option<String>,
+    pub race: Option<Race>,
+    #[serde(default)] pub mechanics: Vec<Mechanic>,
+    /// Keywords mentioned in the text without the card having them, e.g. "Give a minion Taunt"
+    #[serde(rename = "referencedTags", default)] pub referenced_tags: Vec<Mechanic>,
+    #[serde(rename = "multiClassGroup")] pub multi_class_group: Option<MultiClassGroup>,
+    /// Classes of multi-class cards
+    #[serde(default)] pub classes: Vec<CardClass>,
+    #[serde(rename = "spellSchool")] pub spell_school: Option<SpellSchool>,
+    pub overload: Option<i32>,
+    #[serde(rename = "howToEarn")] pub how_to_earn: Option<Name>,
+    #[serde(rename = "howToEarnGolden")] pub how_to_earn_golden: Option<Name>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -41,41 +71,289 @@ pub struct Name {
     #[serde(rename = "zhTW")] pub zh_tw: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum Locale {
+    #[serde(rename = "enUS")] EnUs,
+    #[serde(rename = "deDE")] DeDe,
+    #[serde(rename = "esES")] EsEs,
+    #[serde(rename = "esMX")] EsMx,
+    #[serde(rename = "frFR")] FrFr,
+    #[serde(rename = "itIT")] ItIt,
+    #[serde(rename = "jaJP")] JaJp,
+    #[serde(rename = "koKR")] KoKr,
+    #[serde(rename = "plPL")] PlPl,
+    #[serde(rename = "ptBR")] PtBr,
+    #[serde(rename = "ruRU")] RuRu,
+    #[serde(rename = "thTH")] ThTh,
+    #[serde(rename = "zhCN")] ZhCn,
+    #[serde(rename = "zhTW")] ZhTw,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale::EnUs
+    }
+}
+
+impl Locale {
+    /// Locale code as used by the client, e.g. "deDE"
+    pub fn code(&self) -> &'static str {
+        match *self {
+            Locale::EnUs => "enUS",
+            Locale::DeDe => "deDE",
+            Locale::EsEs => "esES",
+            Locale::EsMx => "esMX",
+            Locale::FrFr => "frFR",
+            Locale::ItIt => "itIT",
+            Locale::JaJp => "jaJP",
+            Locale::KoKr => "koKR",
+            Locale::PlPl => "plPL",
+            Locale::PtBr => "ptBR",
+            Locale::RuRu => "ruRU",
+            Locale::ThTh => "thTH",
+            Locale::ZhCn => "zhCN",
+            Locale::ZhTw => "zhTW",
+        }
+    }
+}
+
+impl FromStr for Locale {
+    type Err = error::Error;
+
+    /// Parses locale codes as used by the client, e.g. "deDE"
+    fn from_str(s: &str) -> error::Result<Self> {
+        Ok(match s {
+            "enUS" => Locale::EnUs,
+            "deDE" => Locale::DeDe,
+            "esES" => Locale::EsEs,
+            "esMX" => Locale::EsMx,
+            "frFR" => Locale::FrFr,
+            "itIT" => Locale::ItIt,
+            "jaJP" => Locale::JaJp,
+            "koKR" => Locale::KoKr,
+            "plPL" => Locale::PlPl,
+            "ptBR" => Locale::PtBr,
+            "ruRU" => Locale::RuRu,
+            "thTH" => Locale::ThTh,
+            "zhCN" => Locale::ZhCn,
+            "zhTW" => Locale::ZhTw,
+            _ => {
+                return Err(error::Error::NotImplementedError(format!(
+                    "Unknown locale {}",
+                    s
+                )));
+            }
+        })
+    }
+}
+
+/// Quality of a card copy, golden cards use their own frame, banner and gem textures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Premium {
+    Normal,
+    Golden,
+}
+
+impl Default for Premium {
+    fn default() -> Self {
+        Premium::Normal
+    }
+}
+
+impl Premium {
+    /// Name of the texture to use for this quality, golden textures have a "_Premium" suffix
+    pub fn texture_name(&self, name: &str) -> String {
+        match *self {
+            Premium::Normal => name.to_string(),
+            Premium::Golden => format!("{}_Premium", name),
+        }
+    }
+}
+
+impl Name {
+    /// Name with the same text in every locale
+    pub fn untranslated(text: &str) -> Self {
+        Name {
+            en_us: text.to_string(),
+            de_de: None,
+            es_es: None,
+            es_mx: None,
+            fr_fr: None,
+            it_it: None,
+            ja_jp: None,
+            ko_kr: None,
+            pl_pl: None,
+            pt_br: None,
+            ru_ru: None,
+            th_th: None,
+            zh_cn: None,
+            zh_tw: None,
+        }
+    }
+
+    /// Returns the translation for the locale if there is one
+    pub fn get(&self, locale: Locale) -> Option<&str> {
+        let translation = match locale {
+            Locale::EnUs => Some(&self.en_us),
+            Locale::DeDe => self.de_de.as_ref(),
+            Locale::EsEs => self.es_es.as_ref(),
+            Locale::EsMx => self.es_mx.as_ref(),
+            Locale::FrFr => self.fr_fr.as_ref(),
+            Locale::ItIt => self.it_it.as_ref(),
+            Locale::JaJp => self.ja_jp.as_ref(),
+            Locale::KoKr => self.ko_kr.as_ref(),
+            Locale::PlPl => self.pl_pl.as_ref(),
+            Locale::PtBr => self.pt_br.as_ref(),
+            Locale::RuRu => self.ru_ru.as_ref(),
+            Locale::ThTh => self.th_th.as_ref(),
+            Locale::ZhCn => self.zh_cn.as_ref(),
+            Locale::ZhTw => self.zh_tw.as_ref(),
+        };
+        translation.map(|s| s.as_str()).filter(|s| !s.is_empty())
+    }
+
+    /// Returns the translation for the locale, falls back to enUS if it is missing
+    pub fn localized(&self, locale: Locale) -> &str {
+        self.get(locale).unwrap_or(&self.en_us)
+    }
+
+    /// Texts of every locale that has one, starting with enUS
+    pub fn translations(&self) -> Vec<&str> {
+        let translations = vec![
+            &self.de_de,
+            &self.es_es,
+            &self.es_mx,
+            &self.fr_fr,
+            &self.it_it,
+            &self.ja_jp,
+            &self.ko_kr,
+            &self.pl_pl,
+            &self.pt_br,
+            &self.ru_ru,
+            &self.th_th,
+            &self.zh_cn,
+            &self.zh_tw,
+        ];
+        let mut texts = vec![self.en_us.as_str()];
+        texts.extend(
+            translations
+                .into_iter()
+                .filter_map(|translation| translation.as_ref())
+                .map(|text| text.as_str())
+                .filter(|text| !text.is_empty()),
+        );
+        texts
+    }
+}
+
+/// Classes of the card database, classes added after this crate are kept as `Unknown`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum CardClass {
-    #[serde(rename = "NEUTRAL")] Neutral,
-    #[serde(rename = "DRUID")] Druid,
-    #[serde(rename = "DEATHKNIGHT")] Deathknight,
-    #[serde(rename = "SHAMAN")] Shaman,
-    #[serde(rename = "PALADIN")] Paladin,
-    #[serde(rename = "WARRIOR")] Warrior,
-    #[serde(rename = "PRIEST")] Priest,
-    #[serde(rename = "HUNTER")] Hunter,
-    #[serde(rename = "MAGE")] Mage,
-    #[serde(rename = "WARLOCK")] Warlock,
-    #[serde(rename = "ROGUE")] Rogue,
-    #[serde(rename = "DREAM")] Dream,
+    Neutral,
+    Druid,
+    Deathknight,
+    Shaman,
+    Paladin,
+    Warrior,
+    Priest,
+    Hunter,
+    Mage,
+    Warlock,
+    Rogue,
+    Dream,
+    Unknown(String),
 }
 
-#[derive(Debug, Deserialize)]
+impl<'a> From<&'a str> for CardClass {
+    fn from(name: &str) -> Self {
+        match name {
+            "NEUTRAL" => CardClass::Neutral,
+            "DRUID" => CardClass::Druid,
+            "DEATHKNIGHT" => CardClass::Deathknight,
+            "SHAMAN" => CardClass::Shaman,
+            "PALADIN" => CardClass::Paladin,
+            "WARRIOR" => CardClass::Warrior,
+            "PRIEST" => CardClass::Priest,
+            "HUNTER" => CardClass::Hunter,
+            "MAGE" => CardClass::Mage,
+            "WARLOCK" => CardClass::Warlock,
+            "ROGUE" => CardClass::Rogue,
+            "DREAM" => CardClass::Dream,
+            _ => CardClass::Unknown(name.to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CardClass {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(CardClass::from(name.as_str()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum CardRarity {
     FREE,
     COMMON,
     RARE,
     EPIC,
     LEGENDARY,
+    Unknown(String),
 }
 
-#[derive(Debug, Deserialize)]
+impl<'a> From<&'a str> for CardRarity {
+    fn from(name: &str) -> Self {
+        match name {
+            "FREE" => CardRarity::FREE,
+            "COMMON" => CardRarity::COMMON,
+            "RARE" => CardRarity::RARE,
+            "EPIC" => CardRarity::EPIC,
+            "LEGENDARY" => CardRarity::LEGENDARY,
+            _ => CardRarity::Unknown(name.to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CardRarity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(CardRarity::from(name.as_str()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum CardType {
-    #[serde(rename = "MINION")] Minion,
-    #[serde(rename = "SPELL")] Spell,
-    #[serde(rename = "WEAPON")] Weapon,
-    #[serde(rename = "ENCHANTMENT")] Enchantment,
-    #[serde(rename = "HERO_POWER")] HeroPower,
-    #[serde(rename = "HERO")] Hero,
+    Minion,
+    Spell,
+    Weapon,
+    Enchantment,
+    HeroPower,
+    Hero,
+    Unknown(String),
 }
-#[derive(Debug, Deserialize)]
+
+impl<'a> From<&'a str> for CardType {
+    fn from(name: &str) -> Self {
+        match name {
+            "MINION" => CardType::Minion,
+            "SPELL" => CardType::Spell,
+            "WEAPON" => CardType::Weapon,
+            "ENCHANTMENT" => CardType::Enchantment,
+            "HERO_POWER" => CardType::HeroPower,
+            "HERO" => CardType::Hero,
+            _ => CardType::Unknown(name.to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CardType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(CardType::from(name.as_str()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum CardSet {
     CORE,
     EXPERT1,
@@ -90,22 +368,785 @@ pub enum CardSet {
     LOE,
     KARA,
     TGT,
-    #[serde(rename = "HERO_SKINS")] HeroSkins,
+    HeroSkins,
     OG,
     MISSIONS,
     HOF,
+    Unknown(String),
+}
+
+impl<'a> From<&'a str> for CardSet {
+    fn from(name: &str) -> Self {
+        match name {
+            "CORE" => CardSet::CORE,
+            "EXPERT1" => CardSet::EXPERT1,
+            "NAXX" => CardSet::NAXX,
+            "TB" => CardSet::TB,
+            "GANGS" => CardSet::GANGS,
+            "ICECROWN" => CardSet::ICECROWN,
+            "UNGORO" => CardSet::UNGORO,
+            "CREDITS" => CardSet::CREDITS,
+            "GVG" => CardSet::GVG,
+            "BRM" => CardSet::BRM,
+            "LOE" => CardSet::LOE,
+            "KARA" => CardSet::KARA,
+            "TGT" => CardSet::TGT,
+            "HERO_SKINS" => CardSet::HeroSkins,
+            "OG" => CardSet::OG,
+            "MISSIONS" => CardSet::MISSIONS,
+            "HOF" => CardSet::HOF,
+            _ => CardSet::Unknown(name.to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CardSet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(CardSet::from(name.as_str()))
+    }
+}
+
+/// Minion types written on the banner below the text
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Race {
+    Murloc,
+    Demon,
+    Mechanical,
+    Elemental,
+    Pirate,
+    Beast,
+    Totem,
+    Dragon,
+    All,
+    Unknown(String),
+}
+
+impl<'a> From<&'a str> for Race {
+    fn from(name: &str) -> Self {
+        match name {
+            "MURLOC" => Race::Murloc,
+            "DEMON" => Race::Demon,
+            "MECHANICAL" => Race::Mechanical,
+            "ELEMENTAL" => Race::Elemental,
+            "PIRATE" => Race::Pirate,
+            "BEAST" => Race::Beast,
+            "TOTEM" => Race::Totem,
+            "DRAGON" => Race::Dragon,
+            "ALL" => Race::All,
+            _ => Race::Unknown(name.to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Race {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(Race::from(name.as_str()))
+    }
+}
+
+/// Keywords and other game tags of the card
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Mechanic {
+    Adapt,
+    AdjacentBuff,
+    AffectedBySpellPower,
+    Aura,
+    Battlecry,
+    CantAttack,
+    CantBeTargetedByHeroPowers,
+    CantBeTargetedBySpells,
+    Charge,
+    ChooseOne,
+    Combo,
+    Deathrattle,
+    Discover,
+    DivineShield,
+    Echo,
+    Enraged,
+    EvilGlow,
+    Forgetful,
+    Freeze,
+    Immune,
+    Inspire,
+    JadeGolem,
+    Lifesteal,
+    Morph,
+    Overload,
+    Poisonous,
+    Quest,
+    ReceivesDoubleSpelldamageBonus,
+    Recruit,
+    Ritual,
+    Secret,
+    Silence,
+    Spellpower,
+    StartOfGame,
+    Stealth,
+    TagOneTurnEffect,
+    Taunt,
+    Topdeck,
+    Untouchable,
+    Windfury,
+    Unknown(String),
+}
+
+impl<'a> From<&'a str> for Mechanic {
+    fn from(name: &str) -> Self {
+        match name {
+            "ADAPT" => Mechanic::Adapt,
+            "ADJACENT_BUFF" => Mechanic::AdjacentBuff,
+            "AFFECTED_BY_SPELL_POWER" => Mechanic::AffectedBySpellPower,
+            "AURA" => Mechanic::Aura,
+            "BATTLECRY" => Mechanic::Battlecry,
+            "CANT_ATTACK" => Mechanic::CantAttack,
+            "CANT_BE_TARGETED_BY_HERO_POWERS" => Mechanic::CantBeTargetedByHeroPowers,
+            "CANT_BE_TARGETED_BY_SPELLS" => Mechanic::CantBeTargetedBySpells,
+            "CHARGE" => Mechanic::Charge,
+            "CHOOSE_ONE" => Mechanic::ChooseOne,
+            "COMBO" => Mechanic::Combo,
+            "DEATHRATTLE" => Mechanic::Deathrattle,
+            "DISCOVER" => Mechanic::Discover,
+            "DIVINE_SHIELD" => Mechanic::DivineShield,
+            "ECHO" => Mechanic::Echo,
+            "ENRAGED" => Mechanic::Enraged,
+            "EVIL_GLOW" => Mechanic::EvilGlow,
+            "FORGETFUL" => Mechanic::Forgetful,
+            "FREEZE" => Mechanic::Freeze,
+            "IMMUNE" => Mechanic::Immune,
+            "INSPIRE" => Mechanic::Inspire,
+            "JADE_GOLEM" => Mechanic::JadeGolem,
+            "LIFESTEAL" => Mechanic::Lifesteal,
+            "MORPH" => Mechanic::Morph,
+            "OVERLOAD" => Mechanic::Overload,
+            "POISONOUS" => Mechanic::Poisonous,
+            "QUEST" => Mechanic::Quest,
+            "RECEIVES_DOUBLE_SPELLDAMAGE_BONUS" => Mechanic::ReceivesDoubleSpelldamageBonus,
+            "RECRUIT" => Mechanic::Recruit,
+            "RITUAL" => Mechanic::Ritual,
+            "SECRET" => Mechanic::Secret,
+            "SILENCE" => Mechanic::Silence,
+            "SPELLPOWER" => Mechanic::Spellpower,
+            "START_OF_GAME" => Mechanic::StartOfGame,
+            "STEALTH" => Mechanic::Stealth,
+            "TAG_ONE_TURN_EFFECT" => Mechanic::TagOneTurnEffect,
+            "TAUNT" => Mechanic::Taunt,
+            "TOPDECK" => Mechanic::Topdeck,
+            "UNTOUCHABLE" => Mechanic::Untouchable,
+            "WINDFURY" => Mechanic::Windfury,
+            _ => Mechanic::Unknown(name.to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Mechanic {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(Mechanic::from(name.as_str()))
+    }
+}
+
+/// Tri-class groups of Mean Streets of Gadgetzan
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MultiClassGroup {
+    GrimyGoons,
+    JadeLotus,
+    Kabal,
+    Unknown(String),
+}
+
+impl<'a> From<&'a str> for MultiClassGroup {
+    fn from(name: &str) -> Self {
+        match name {
+            "GRIMY_GOONS" => MultiClassGroup::GrimyGoons,
+            "JADE_LOTUS" => MultiClassGroup::JadeLotus,
+            "KABAL" => MultiClassGroup::Kabal,
+            _ => MultiClassGroup::Unknown(name.to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for MultiClassGroup {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(MultiClassGroup::from(name.as_str()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SpellSchool {
+    Arcane,
+    Fel,
+    Fire,
+    Frost,
+    Holy,
+    Nature,
+    Shadow,
+    Unknown(String),
+}
+
+impl<'a> From<&'a str> for SpellSchool {
+    fn from(name: &str) -> Self {
+        match name {
+            "ARCANE" => SpellSchool::Arcane,
+            "FEL" => SpellSchool::Fel,
+            "FIRE" => SpellSchool::Fire,
+            "FROST" => SpellSchool::Frost,
+            "HOLY" => SpellSchool::Holy,
+            "NATURE" => SpellSchool::Nature,
+            "SHADOW" => SpellSchool::Shadow,
+            _ => SpellSchool::Unknown(name.to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SpellSchool {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(SpellSchool::from(name.as_str()))
+    }
+}
+
+impl Card {
+    /// Legendary cards and cards with the ELITE tag get the dragon frame
+    pub fn is_elite(&self) -> bool {
+        match self.rarity {
+            Some(CardRarity::LEGENDARY) => true,
+            _ => self.elite,
+        }
+    }
+}
+
+/// Definition of a card that is not in the card database
+#[derive(Debug, Clone)]
+pub struct CustomCard {
+    pub name: String,
+    /// Rules text, it may contain the same markup as the texts of the card database
+    pub text: String,
+    pub cost: Option<i32>,
+    pub card_class: CardClass,
+    pub card_type: CardType,
+    /// Cards without rarity have no rarity gem
+    pub rarity: Option<CardRarity>,
+    pub attack: Option<i32>,
+    pub health: Option<i32>,
+    pub durability: Option<i32>,
+    pub armor: Option<i32>,
+    /// Draws the dragon frame of legendary cards on cards of other rarities
+    pub elite: bool,
+}
+
+impl CustomCard {
+    pub fn new(name: &str, card_class: CardClass, card_type: CardType) -> Self {
+        CustomCard {
+            name: name.to_string(),
+            text: String::new(),
+            cost: None,
+            card_class: card_class,
+            card_type: card_type,
+            rarity: None,
+            attack: None,
+            health: None,
+            durability: None,
+            armor: None,
+            elite: false,
+        }
+    }
+
+    /// Card as it would be read from the card database
+    pub fn to_card(&self) -> Card {
+        Card {
+            id: format!("CUSTOM_{}", self.name),
+            card_class: Some(self.card_class.clone()),
+            player_class: Some(self.card_class.clone()),
+            name: Some(Name::untranslated(&self.name)),
+            text: Some(Name::untranslated(&self.text)),
+            rarity: self.rarity.clone(),
+            cost: self.cost,
+            card_type: Some(self.card_type.clone()),
+            attack: self.attack,
+            health: self.health,
+            durability: self.durability,
+            armor: self.armor,
+            elite: self.elite,
+            ..Default::default()
+        }
+    }
 }
 
 impl CardDb {
+    /// Cards of the embedded res/cards.json
     pub fn new() -> error::Result<Self> {
-        let cards: Vec<Card> = serde_json::from_reader(CARDDEF_DATA)?;
-        Ok(CardDb {
+        CardDb::from_reader(CARDDEF_DATA)
+    }
+
+    /// Reads a JSON array of cards in the hearthstonejson schema
+    pub fn from_reader<R: Read>(reader: R) -> error::Result<Self> {
+        let cards: Vec<Card> = serde_json::from_reader(reader)?;
+        let mut db = CardDb {
             cards: cards.into_iter().fold(HashMap::new(), |mut map, card| {
                 {
                     map.insert(card.id.clone(), card);
                 }
                 map
             }),
-        })
+            dbf_ids: HashMap::new(),
+            names: HashMap::new(),
+        };
+        db.build_indexes();
+        Ok(db)
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> error::Result<Self> {
+        let file = File::open(path)?;
+        CardDb::from_reader(BufReader::new(file))
+    }
+
+    /// Adds the cards of the other database, its cards replace the ones with the same id
+    pub fn merge(&mut self, other: CardDb) {
+        self.cards.extend(other.cards);
+        self.build_indexes();
+    }
+
+    pub fn get(&self, card_id: &str) -> Option<&Card> {
+        self.cards.get(card_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    pub fn contains(&self, card_id: &str) -> bool {
+        self.cards.contains_key(card_id)
+    }
+
+    pub fn cards(&self) -> Values<String, Card> {
+        self.cards.values()
+    }
+
+    /// Ids and cards in arbitrary order, like iterating over the map of cards
+    pub fn iter(&self) -> Iter<String, Card> {
+        self.cards.iter()
+    }
+
+    /// Cards by id, read only because the indexes are built from them
+    pub fn card_map(&self) -> &HashMap<String, Card> {
+        &self.cards
+    }
+
+    pub fn by_dbf_id(&self, dbf_id: u32) -> Option<&Card> {
+        self.dbf_ids.get(&dbf_id).and_then(|id| self.cards.get(id))
+    }
+
+    /// Cards with the given name in any locale, e.g. a minion and the tavern brawl copies of it.
+    /// Case, spaces and punctuation are ignored.
+    pub fn by_name(&self, name: &str) -> Vec<&Card> {
+        self.cards_named(&normalize_name(name))
+    }
+
+    /// Cards with the name closest to the given one, for names with typos or missing words
+    pub fn fuzzy_by_name(&self, name: &str) -> Vec<&Card> {
+        let name = normalize_name(name);
+        let max_distance = (name.chars().count() / 4).max(1);
+        let closest = self.names
+            .keys()
+            .map(|candidate| (edit_distance(&name, candidate), candidate))
+            .filter(|&(distance, _)| distance <= max_distance)
+            .min();
+        match closest {
+            Some((_, candidate)) => self.cards_named(candidate),
+            None => Vec::new(),
+        }
+    }
+
+    /// The card a player most likely means by the name, exact matches are preferred over fuzzy
+    /// ones and collectible cards over the others
+    pub fn find_by_name(&self, name: &str) -> Option<&Card> {
+        let mut cards = self.by_name(name);
+        if cards.is_empty() {
+            cards = self.fuzzy_by_name(name);
+        }
+        cards
+            .iter()
+            .find(|card| card.collectible)
+            .or(cards.first())
+            .cloned()
+    }
+
+    /// Starts a query over every card, e.g.
+    /// `db.query().collectible().class(CardClass::Mage).cost(0..=3)`
+    pub fn query(&self) -> CardQuery {
+        CardQuery {
+            cards: self.cards.values(),
+            filter: QueryFilter::default(),
+        }
+    }
+
+    fn cards_named(&self, normalized_name: &str) -> Vec<&Card> {
+        match self.names.get(normalized_name) {
+            Some(ids) => ids.iter().filter_map(|id| self.cards.get(id)).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn build_indexes(&mut self) {
+        self.dbf_ids.clear();
+        self.names.clear();
+        for card in self.cards.values() {
+            if let Some(dbf_id) = card.dbf_id {
+                self.dbf_ids.insert(dbf_id, card.id.clone());
+            }
+            if let Some(ref name) = card.name {
+                // most cards have the same name in several locales
+                let mut names: Vec<String> = name.translations()
+                    .into_iter()
+                    .map(normalize_name)
+                    .filter(|normalized| !normalized.is_empty())
+                    .collect();
+                names.sort();
+                names.dedup();
+                for name in names {
+                    self.names
+                        .entry(name)
+                        .or_insert_with(Vec::new)
+                        .push(card.id.clone());
+                }
+            }
+        }
+        // the order of the hash map is random, sorted ids give the same results every run
+        for ids in self.names.values_mut() {
+            ids.sort();
+        }
+    }
+}
+
+/// Iterator over the cards of a database that pass every filter of the query
+pub struct CardQuery<'a> {
+    cards: Values<'a, String, Card>,
+    filter: QueryFilter,
+}
+
+#[derive(Default)]
+struct QueryFilter {
+    collectible: bool,
+    card_class: Option<CardClass>,
+    set: Option<CardSet>,
+    card_type: Option<CardType>,
+    rarity: Option<CardRarity>,
+    cost: Option<(Bound<i32>, Bound<i32>)>,
+}
+
+impl<'a> CardQuery<'a> {
+    /// Cards that can be put into decks
+    pub fn collectible(mut self) -> Self {
+        self.filter.collectible = true;
+        self
+    }
+
+    /// Cards of the class, multi-class cards match each of their classes
+    pub fn class(mut self, card_class: CardClass) -> Self {
+        self.filter.card_class = Some(card_class);
+        self
+    }
+
+    pub fn set(mut self, set: CardSet) -> Self {
+        self.filter.set = Some(set);
+        self
+    }
+
+    pub fn card_type(mut self, card_type: CardType) -> Self {
+        self.filter.card_type = Some(card_type);
+        self
+    }
+
+    pub fn rarity(mut self, rarity: CardRarity) -> Self {
+        self.filter.rarity = Some(rarity);
+        self
+    }
+
+    /// Cards with a cost in the range, cards without cost never match
+    pub fn cost<R: RangeBounds<i32>>(mut self, range: R) -> Self {
+        self.filter.cost = Some((owned_bound(range.start_bound()), owned_bound(range.end_bound())));
+        self
+    }
+}
+
+impl<'a> Iterator for CardQuery<'a> {
+    type Item = &'a Card;
+
+    fn next(&mut self) -> Option<&'a Card> {
+        let filter = &self.filter;
+        self.cards.find(|card| filter.matches(card))
+    }
+}
+
+impl QueryFilter {
+    fn matches(&self, card: &Card) -> bool {
+        if self.collectible && !card.collectible {
+            return false;
+        }
+        if let Some(ref card_class) = self.card_class {
+            if card.card_class.as_ref() != Some(card_class) && !card.classes.contains(card_class) {
+                return false;
+            }
+        }
+        if self.set.is_some() && card.set != self.set {
+            return false;
+        }
+        if self.card_type.is_some() && card.card_type != self.card_type {
+            return false;
+        }
+        if self.rarity.is_some() && card.rarity != self.rarity {
+            return false;
+        }
+        if let Some((start, end)) = self.cost {
+            let cost = match card.cost {
+                Some(cost) => cost,
+                None => return false,
+            };
+            let above_start = match start {
+                Bound::Included(start) => cost >= start,
+                Bound::Excluded(start) => cost > start,
+                Bound::Unbounded => true,
+            };
+            let below_end = match end {
+                Bound::Included(end) => cost <= end,
+                Bound::Excluded(end) => cost < end,
+                Bound::Unbounded => true,
+            };
+            if !above_start || !below_end {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Lowercase name without spaces and punctuation, "Ragnaros, the Firelord" -> "ragnarosthefirelord"
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+// Levenshtein distance counted in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..b.len() + 1).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, a_char) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + if a_char == *b_char { 0 } else { 1 };
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        ::std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+fn owned_bound(bound: Bound<&i32>) -> Bound<i32> {
+    match bound {
+        Bound::Included(value) => Bound::Included(*value),
+        Bound::Excluded(value) => Bound::Excluded(*value),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+// Databases keep the shape they had before the indexes: an object with the cards by id
+impl<'de> Deserialize<'de> for CardDb {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Cards {
+            cards: HashMap<String, Card>,
+        }
+
+        let cards = Cards::deserialize(deserializer)?;
+        let mut db = CardDb {
+            cards: cards.cards,
+            dbf_ids: HashMap::new(),
+            names: HashMap::new(),
+        };
+        db.build_indexes();
+        Ok(db)
+    }
+}
+
+impl<'a> IntoIterator for &'a CardDb {
+    type Item = (&'a String, &'a Card);
+    type IntoIter = Iter<'a, String, Card>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.cards.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_overrides_cards() {
+        let mut db = CardDb::from_reader(
+            r#"[
+                {"id": "CS2_029", "name": {"enUS": "Fireball"}, "cost": 4, "type": "SPELL"},
+                {"id": "CS2_024", "name": {"enUS": "Frostbolt"}, "cost": 2, "type": "SPELL"}
+            ]"#.as_bytes(),
+        ).unwrap();
+        let homebrew = CardDb::from_reader(
+            r#"[
+                {"id": "CS2_029", "name": {"enUS": "Fireball"}, "cost": 3, "type": "SPELL"},
+                {"id": "HB_001", "name": {"enUS": "Snowball"}, "cost": 1, "type": "SPELL"}
+            ]"#.as_bytes(),
+        ).unwrap();
+        db.merge(homebrew);
+
+        assert_eq!(db.cards.len(), 3);
+        assert_eq!(db.cards["CS2_029"].cost, Some(3));
+        assert_eq!(db.cards["CS2_024"].cost, Some(2));
+        assert_eq!(db.cards["HB_001"].card_type, Some(CardType::Spell));
+    }
+
+    #[test]
+    fn iterate_cards() {
+        let db = CardDb::from_reader(
+            r#"[
+                {"id": "CS2_029", "name": {"enUS": "Fireball"}, "cost": 4, "type": "SPELL"},
+                {"id": "CS2_024", "name": {"enUS": "Frostbolt"}, "cost": 2, "type": "SPELL"}
+            ]"#.as_bytes(),
+        ).unwrap();
+
+        assert_eq!(db.len(), 2);
+        assert!(db.contains("CS2_024"));
+        assert_eq!(db.card_map()["CS2_029"].cost, Some(4));
+        let mut ids: Vec<&str> = db.iter().map(|(id, _)| id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["CS2_024", "CS2_029"]);
+        assert_eq!((&db).into_iter().count(), db.cards().count());
+
+        let db: CardDb = serde_json::from_str(
+            r#"{"cards": {
+                "CS2_029": {"id": "CS2_029", "dbfId": 315, "name": {"enUS": "Fireball"}}
+            }}"#,
+        ).unwrap();
+        assert_eq!(db.by_dbf_id(315).unwrap().id, "CS2_029");
+    }
+
+    #[test]
+    fn keep_unknown_values() {
+        let db = CardDb::from_reader(
+            r#"[
+                {"id": "BT_035", "name": {"enUS": "Chaos Strike"}, "cardClass": "DEMONHUNTER",
+                 "set": "BLACK_TEMPLE", "rarity": "COMMON", "type": "SPELL"},
+                {"id": "LOOT_103", "name": {"enUS": "Lesser Ruby Spellstone"},
+                 "set": "LOOTAPALOOZA", "rarity": "RARE", "type": "SPELLSTONE"}
+            ]"#.as_bytes(),
+        ).unwrap();
+
+        let chaos_strike = &db.cards["BT_035"];
+        assert_eq!(
+            chaos_strike.card_class,
+            Some(CardClass::Unknown("DEMONHUNTER".to_string()))
+        );
+        assert_eq!(chaos_strike.rarity, Some(CardRarity::COMMON));
+        assert_eq!(chaos_strike.card_type, Some(CardType::Spell));
+        let spellstone = &db.cards["LOOT_103"];
+        assert_eq!(
+            spellstone.set,
+            Some(CardSet::Unknown("LOOTAPALOOZA".to_string()))
+        );
+        assert_eq!(
+            spellstone.card_type,
+            Some(CardType::Unknown("SPELLSTONE".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_card_metadata() {
+        let db = CardDb::from_reader(
+            r#"[{
+                "id": "HB_002", "dbfId": 90002, "name": {"enUS": "Jade Murloc"},
+                "flavor": {"enUS": "Mrglmrgl."},
+                "artist": "Someone", "cardClass": "NEUTRAL", "type": "MINION",
+                "classes": ["DRUID", "ROGUE", "SHAMAN"], "multiClassGroup": "JADE_LOTUS",
+                "mechanics": ["BATTLECRY", "DEATHRATTLE", "JADE_GOLEM"],
+                "referencedTags": ["TAUNT"], "race": "MURLOC", "attack": 2, "health": 2
+            }]"#.as_bytes(),
+        ).unwrap();
+
+        let card = &db.cards["HB_002"];
+        assert_eq!(card.dbf_id, Some(90002));
+        assert_eq!(card.artist, Some("Someone".to_string()));
+        assert_eq!(card.classes.len(), 3);
+        assert_eq!(card.multi_class_group, Some(MultiClassGroup::JadeLotus));
+        assert_eq!(card.mechanics[2], Mechanic::JadeGolem);
+        assert_eq!(card.referenced_tags, vec![Mechanic::Taunt]);
+        assert_eq!(card.race, Some(Race::Murloc));
+        assert_eq!(card.spell_school, None);
+        assert!(card.flavor.is_some());
+    }
+
+    #[test]
+    fn query_cards() {
+        let db = CardDb::from_reader(
+            r#"[
+                {"id": "CS2_029", "dbfId": 315, "name": {"enUS": "Fireball"}, "cost": 4,
+                 "type": "SPELL", "cardClass": "MAGE", "set": "CORE", "collectible": true},
+                {"id": "CS2_024", "dbfId": 662, "name": {"enUS": "Frostbolt"}, "cost": 2,
+                 "type": "SPELL", "cardClass": "MAGE", "set": "CORE", "collectible": true},
+                {"id": "TB_Fireball", "name": {"enUS": "Fireball"}, "cost": 4, "type": "SPELL",
+                 "cardClass": "MAGE", "set": "TB"},
+                {"id": "CFM_902", "dbfId": 40408, "name": {"enUS": "Aya Blackpaw"}, "cost": 6,
+                 "type": "MINION", "cardClass": "NEUTRAL", "set": "GANGS", "collectible": true,
+                 "classes": ["DRUID", "ROGUE", "SHAMAN"]}
+            ]"#.as_bytes(),
+        ).unwrap();
+
+        let mut mage_spells: Vec<&str> = db.query()
+            .collectible()
+            .class(CardClass::Mage)
+            .card_type(CardType::Spell)
+            .map(|card| card.id.as_str())
+            .collect();
+        mage_spells.sort();
+        assert_eq!(mage_spells, vec!["CS2_024", "CS2_029"]);
+        assert_eq!(db.query().cost(0..=3).count(), 1);
+        assert_eq!(db.query().cost(4..).count(), 3);
+        assert_eq!(db.query().set(CardSet::TB).count(), 1);
+        assert_eq!(db.query().class(CardClass::Rogue).count(), 1);
+
+        assert_eq!(db.by_dbf_id(662).unwrap().id, "CS2_024");
+        let fireballs: Vec<&str> = db.by_name("Fireball")
+            .iter()
+            .map(|card| card.id.as_str())
+            .collect();
+        assert_eq!(fireballs, vec!["CS2_029", "TB_Fireball"]);
+    }
+
+    #[test]
+    fn find_cards_by_name() {
+        let db = CardDb::from_reader(
+            r#"[
+                {"id": "EX1_298", "name": {"enUS": "Ragnaros the Firelord",
+                 "deDE": "Ragnaros der Feuerfürst"}, "collectible": true},
+                {"id": "TB_Ragnaros", "name": {"enUS": "Ragnaros the Firelord"}},
+                {"id": "EX1_289", "name": {"enUS": "Ice Barrier", "frFR": "Barrière de glace"},
+                 "collectible": true}
+            ]"#.as_bytes(),
+        ).unwrap();
+
+        assert_eq!(db.by_name("ragnaros, the firelord").len(), 2);
+        assert_eq!(db.by_name("Ragnaros der Feuerfürst")[0].id, "EX1_298");
+        assert_eq!(db.by_name("barriere de glace").len(), 0);
+        assert_eq!(db.fuzzy_by_name("barriere de glace")[0].id, "EX1_289");
+        assert_eq!(db.find_by_name("Ragnaros Firelord").unwrap().id, "EX1_298");
+        assert!(db.find_by_name("Fireball").is_none());
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
     }
 }
@@ -0,0 +1,229 @@
+//! Cards as a stack of named layers that can be changed before they are flattened
+
+use builder::TRANSPARENT_COLOR;
+use error::{Error, Result};
+use layout::{Blend, Flip};
+use openraster::{self, RasterLayer};
+use sfml::graphics::{BlendMode, Image, RenderStates, RenderTarget, RenderTexture, Sprite, Texture,
+                     Transform, Transformable};
+use sfml::system::Vector2f;
+use std::io::{Seek, Write};
+use utils::SpriteTransforms;
+
+/// Placement of a layer's image on the card
+#[derive(Debug, Clone, Copy)]
+pub struct LayerTransform {
+    /// Top left corner of the layer
+    pub position: Vector2f,
+    pub scale: Vector2f,
+    pub flip: Flip,
+}
+
+pub struct CompositionLayer {
+    /// Name of the layer in the card layout, e.g. "portrait" or "mana_gem"
+    pub name: String,
+    pub image: Image,
+    pub transform: LayerTransform,
+    pub blend: Blend,
+    pub visible: bool,
+}
+
+impl CompositionLayer {
+    pub fn new(name: &str, image: Image, position: Vector2f) -> Self {
+        CompositionLayer {
+            name: name.to_string(),
+            image: image,
+            transform: LayerTransform {
+                position: position,
+                scale: Vector2f::new(1f32, 1f32),
+                flip: Flip::None,
+            },
+            blend: Blend::Alpha,
+            visible: true,
+        }
+    }
+
+    /// Size of the layer on the card
+    pub fn size(&self) -> Vector2f {
+        Vector2f::new(
+            self.image.size().x as f32 * self.transform.scale.x,
+            self.image.size().y as f32 * self.transform.scale.y,
+        )
+    }
+
+    fn sprite<'t>(&self, texture: &'t Texture) -> Sprite<'t> {
+        let mut sprite = Sprite::with_texture(texture);
+        // the sprite helpers are named after the axis the image is turned around
+        match self.transform.flip {
+            Flip::Horizontal => sprite.flip_vertically(),
+            Flip::Vertical => sprite.flip_horizontally(),
+            Flip::None => {}
+        };
+        sprite.set_scale(self.transform.scale);
+        sprite.set_position(self.transform.position);
+        sprite
+    }
+
+    // Image of the layer with its flip and scale applied, placed at a whole pixel offset
+    fn rasterize(&self) -> Result<(Image, i32, i32)> {
+        let position = self.transform.position;
+        let offset = Vector2f::new(position.x.floor(), position.y.floor());
+        let size = self.size();
+
+        let mut canvas = RenderTexture::new(
+            ((size.x + position.x - offset.x).ceil() as u32).max(1),
+            ((size.y + position.y - offset.y).ceil() as u32).max(1),
+            false,
+        ).ok_or(Error::SFMLError)?;
+        canvas.clear(&TRANSPARENT_COLOR);
+        canvas.set_smooth(true);
+
+        let mut texture = Texture::from_image(&self.image).ok_or(Error::SFMLError)?;
+        texture.set_smooth(true);
+        let mut sprite = self.sprite(&texture);
+        sprite.set_position(position - offset);
+        canvas.draw(&sprite);
+        canvas.display();
+
+        let image = canvas.texture().copy_to_image().ok_or(Error::SFMLError)?;
+        Ok((image, offset.x as i32, offset.y as i32))
+    }
+}
+
+/// Layers of a card from bottom to top
+pub struct CardComposition {
+    pub width: u32,
+    pub height: u32,
+    pub layers: Vec<CompositionLayer>,
+}
+
+impl CardComposition {
+    pub fn new(width: u32, height: u32) -> Self {
+        CardComposition {
+            width: width,
+            height: height,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Adds a layer on top of the others
+    pub fn push_layer(&mut self, layer: CompositionLayer) {
+        self.layers.push(layer);
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&CompositionLayer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut CompositionLayer> {
+        self.layers.iter_mut().find(|layer| layer.name == name)
+    }
+
+    pub fn layer_names(&self) -> Vec<&str> {
+        self.layers.iter().map(|layer| layer.name.as_str()).collect()
+    }
+
+    pub fn set_visible(&mut self, name: &str, visible: bool) -> Result<()> {
+        self.find_layer(name)?.visible = visible;
+        Ok(())
+    }
+
+    /// Replaces the image of a layer, the new image is scaled to the size of the old one.
+    /// It is drawn upright: rendered layers are flipped to undo the flip of their meshes, that
+    /// flip is dropped with the old image.
+    pub fn replace_image(&mut self, name: &str, image: Image) -> Result<()> {
+        let layer = self.find_layer(name)?;
+        let size = layer.size();
+        if image.size().x == 0 || image.size().y == 0 {
+            return Err(Error::InvalidAssetError(format!(
+                "Cannot replace layer {} with an empty image",
+                name
+            )));
+        }
+        layer.transform.scale = Vector2f::new(
+            size.x / image.size().x as f32,
+            size.y / image.size().y as f32,
+        );
+        layer.transform.flip = Flip::None;
+        layer.image = image;
+        Ok(())
+    }
+
+    /// Draws the visible layers onto one image
+    pub fn flatten(&self) -> Result<Image> {
+        let mut canvas =
+            RenderTexture::new(self.width, self.height, false).ok_or(Error::SFMLError)?;
+        canvas.clear(&TRANSPARENT_COLOR);
+        canvas.set_smooth(true);
+
+        for layer in self.layers.iter().filter(|layer| layer.visible) {
+            let mut texture = Texture::from_image(&layer.image).ok_or(Error::SFMLError)?;
+            texture.set_smooth(true);
+
+            let sprite = layer.sprite(&texture);
+            let blend_mode = match layer.blend {
+                Blend::Alpha => BlendMode::default(),
+                Blend::Add => BlendMode::ADD,
+                Blend::Multiply => BlendMode::MULTIPLY,
+            };
+            let render_states = RenderStates::new(blend_mode, Transform::default(), None, None);
+            canvas.draw_with_renderstates(&sprite, render_states);
+        }
+
+        canvas.display();
+        Ok(canvas.texture().copy_to_image().ok_or(Error::SFMLError)?)
+    }
+
+    /// Writes the layers into an OpenRaster (.ora) file that image editors open as layers,
+    /// flips and scales are applied to the stored images
+    pub fn write_openraster<W: Write + Seek>(&self, writer: W) -> Result<()> {
+        let mut raster_layers = Vec::with_capacity(self.layers.len());
+        for layer in &self.layers {
+            let (image, x, y) = layer.rasterize()?;
+            raster_layers.push(RasterLayer {
+                name: &layer.name,
+                image: image,
+                x: x,
+                y: y,
+                visible: layer.visible,
+                blend: layer.blend,
+            });
+        }
+        let merged = self.flatten()?;
+        openraster::encode_openraster(self.width, self.height, &raster_layers, &merged, writer)
+    }
+
+    fn find_layer(&mut self, name: &str) -> Result<&mut CompositionLayer> {
+        self.layer_mut(name)
+            .ok_or(Error::LayerNotFoundError(name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sfml::graphics::Color;
+
+    #[test]
+    fn replaced_images_are_upright() {
+        let mut composition = CardComposition::new(4, 4);
+        let mut layer =
+            CompositionLayer::new("portrait", Image::new(2, 2), Vector2f::new(1f32, 1f32));
+        layer.transform.flip = Flip::Horizontal;
+        composition.push_layer(layer);
+
+        // red, green on top of blue, white
+        let image = Image::create_from_pixels(
+            2,
+            2,
+            &[255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 255],
+        ).unwrap();
+        composition.replace_image("portrait", image).unwrap();
+
+        let flattened = composition.flatten().unwrap();
+        assert_eq!(flattened.pixel_at(1, 1), Color::rgb(255, 0, 0));
+        assert_eq!(flattened.pixel_at(2, 1), Color::rgb(0, 255, 0));
+        assert_eq!(flattened.pixel_at(1, 2), Color::rgb(0, 0, 255));
+        assert_eq!(flattened.pixel_at(2, 2), Color::rgb(255, 255, 255));
+    }
+}
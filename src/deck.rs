@@ -0,0 +1,237 @@
+//! Deck codes as they are copied from the game client
+//!
+//! A deck code is a base64 string of varints: a zero byte, the version, the format, the heroes
+//! and the cards grouped by their number of copies. Cards are referenced by dbfId.
+
+use base64;
+use cards::{Card, CardDb, Locale};
+use error::{Error, Result};
+
+const DECK_CODE_VERSION: u64 = 1;
+// the largest decks hold 40 cards
+const MAX_COPIES: u64 = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Wild,
+    Standard,
+    Unknown(u64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Deck {
+    pub format: Format,
+    /// dbfIds of the heroes
+    pub heroes: Vec<u32>,
+    /// dbfIds of the cards and their number of copies
+    pub cards: Vec<(u32, u32)>,
+}
+
+impl Deck {
+    /// Decodes a deck code, the comment lines of decks exported by the client are skipped
+    pub fn from_code(code: &str) -> Result<Self> {
+        let code = code.lines()
+            .map(|line| line.trim())
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+            .ok_or(Error::InvalidDeckCodeError(format!("The deck code is empty")))?;
+        let bytes = base64::decode(code)?;
+        let mut reader = VarintReader {
+            bytes: &bytes,
+            position: 0,
+        };
+
+        if reader.read()? != 0 {
+            return Err(Error::InvalidDeckCodeError(format!(
+                "Deck codes must start with a zero byte"
+            )));
+        }
+        let version = reader.read()?;
+        if version != DECK_CODE_VERSION {
+            return Err(Error::InvalidDeckCodeError(format!(
+                "Unsupported deck code version {}",
+                version
+            )));
+        }
+        let format = match reader.read()? {
+            1 => Format::Wild,
+            2 => Format::Standard,
+            format => Format::Unknown(format),
+        };
+
+        let mut heroes = Vec::new();
+        for _ in 0..reader.read()? {
+            heroes.push(reader.read_dbf_id()?);
+        }
+
+        let mut cards = Vec::new();
+        for copies in 1..3 {
+            for _ in 0..reader.read()? {
+                cards.push((reader.read_dbf_id()?, copies));
+            }
+        }
+        // cards with more than two copies store their count after the dbfId
+        for _ in 0..reader.read()? {
+            let dbf_id = reader.read_dbf_id()?;
+            cards.push((dbf_id, reader.read_copies()?));
+        }
+
+        Ok(Deck {
+            format: format,
+            heroes: heroes,
+            cards: cards,
+        })
+    }
+
+    /// Number of cards in the deck, copies included
+    pub fn card_count(&self) -> u32 {
+        self.cards
+            .iter()
+            .fold(0u32, |count, &(_, copies)| count.saturating_add(copies))
+    }
+
+    /// Cards of the deck with their number of copies, sorted by cost and by their name in the
+    /// locale like in the client
+    pub fn resolve<'a>(
+        &self,
+        card_db: &'a CardDb,
+        locale: Locale,
+    ) -> Result<Vec<(&'a Card, u32)>> {
+        let mut cards = Vec::with_capacity(self.cards.len());
+        for &(dbf_id, copies) in &self.cards {
+            let card = card_db
+                .by_dbf_id(dbf_id)
+                .ok_or(Error::DbfIdNotFoundError(dbf_id))?;
+            cards.push((card, copies));
+        }
+        cards.sort_by_key(|&(card, _)| {
+            (
+                card.cost.unwrap_or(0),
+                card.name
+                    .as_ref()
+                    .map(|name| name.localized(locale).to_string()),
+            )
+        });
+        Ok(cards)
+    }
+}
+
+struct VarintReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> VarintReader<'a> {
+    // Unsigned LEB128, seven bits per byte with the high bit set on every byte but the last
+    fn read(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = *self.bytes
+                .get(self.position)
+                .ok_or(Error::InvalidDeckCodeError(format!("The deck code is truncated")))?;
+            self.position += 1;
+            if shift >= 64 {
+                return Err(Error::InvalidDeckCodeError(format!(
+                    "Number is too large at byte {}",
+                    self.position
+                )));
+            }
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    fn read_dbf_id(&mut self) -> Result<u32> {
+        let value = self.read()?;
+        if value > u32::max_value() as u64 {
+            return Err(Error::InvalidDeckCodeError(format!("Invalid dbfId {}", value)));
+        }
+        Ok(value as u32)
+    }
+
+    fn read_copies(&mut self) -> Result<u32> {
+        let value = self.read()?;
+        if value > MAX_COPIES {
+            return Err(Error::InvalidDeckCodeError(format!(
+                "Invalid number of copies {}",
+                value
+            )));
+        }
+        Ok(value as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_deck_code() {
+        let deck = Deck::from_code("### Test deck\n# Class: Mage\nAAECAf0EArsClgUB7AcBmggD\n#")
+            .unwrap();
+        assert_eq!(deck.format, Format::Standard);
+        assert_eq!(deck.heroes, vec![637]);
+        assert_eq!(deck.cards, vec![(315, 1), (662, 1), (1004, 2), (1050, 3)]);
+        assert_eq!(deck.card_count(), 7);
+    }
+
+    #[test]
+    fn resolve_cards() {
+        let card_db = CardDb::from_reader(
+            r#"[
+                {"id": "CS2_029", "dbfId": 315, "cost": 4,
+                 "name": {"enUS": "Fireball", "deDE": "Feuerball"}},
+                {"id": "EX1_279", "dbfId": 662, "cost": 4,
+                 "name": {"enUS": "Pyroblast", "deDE": "Explosionsschlag"}}
+            ]"#.as_bytes(),
+        ).unwrap();
+        let deck = Deck {
+            format: Format::Wild,
+            heroes: vec![637],
+            cards: vec![(662, 1), (315, 2)],
+        };
+
+        let ids = |locale: Locale| -> Vec<String> {
+            deck.resolve(&card_db, locale)
+                .unwrap()
+                .iter()
+                .map(|&(card, _)| card.id.clone())
+                .collect()
+        };
+        assert_eq!(ids(Locale::EnUs), vec!["CS2_029", "EX1_279"]);
+        assert_eq!(ids(Locale::DeDe), vec!["EX1_279", "CS2_029"]);
+
+        let mut deck = deck.clone();
+        deck.cards.push((1004, 1));
+        match deck.resolve(&card_db, Locale::EnUs) {
+            Err(Error::DbfIdNotFoundError(1004)) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reject_truncated_code() {
+        match Deck::from_code("AAECAf0EArsClgUB7AcBmg==") {
+            Err(Error::InvalidDeckCodeError(_)) => {}
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reject_invalid_copy_count() {
+        assert_eq!(
+            Deck::from_code("AAECAf0EAAABmggD").unwrap().cards,
+            vec![(1050, 3)]
+        );
+        // 41 copies, and 2^32 + 3 copies that would wrap around to 3
+        for code in &["AAECAf0EAAABmggp", "AAECAf0EAAABmgiDgICAEA=="] {
+            match Deck::from_code(code) {
+                Err(Error::InvalidDeckCodeError(_)) => {}
+                other => panic!("Unexpected result {:?}", other),
+            }
+        }
+    }
+}
@@ -1,7 +1,10 @@
+use base64;
 use std::result;
 use unitypack;
 use glob::{GlobError, PatternError};
 use serde_json;
+use png;
+use zip;
 use std::{io, num};
 
 #[derive(Debug)]
@@ -10,10 +13,17 @@ pub enum Error {
     PathError(Box<GlobError>),
     JsonError(Box<serde_json::Error>),
     IOError(Box<io::Error>),
+    PngError(Box<png::EncodingError>),
+    ZipError(Box<zip::result::ZipError>),
+    Base64Error(Box<base64::DecodeError>),
     CardNotFoundError,
+    DbfIdNotFoundError(u32),
+    LayerNotFoundError(String),
     AssetNotFoundError(String),
     InvalidAssetError(String),
     InvalidCardError,
+    InvalidDeckCodeError(String),
+    InvalidOptionsError(String),
     ObjectTypeError,
     SFMLError,
     NotImplementedError(String),
@@ -38,6 +48,24 @@ impl From<io::Error> for Error {
     }
 }
 
+impl From<png::EncodingError> for Error {
+    fn from(error: png::EncodingError) -> Error {
+        Error::PngError(Box::new(error))
+    }
+}
+
+impl From<zip::result::ZipError> for Error {
+    fn from(error: zip::result::ZipError) -> Error {
+        Error::ZipError(Box::new(error))
+    }
+}
+
+impl From<base64::DecodeError> for Error {
+    fn from(error: base64::DecodeError) -> Error {
+        Error::Base64Error(Box::new(error))
+    }
+}
+
 impl From<num::ParseIntError> for Error {
     fn from(_: num::ParseIntError) -> Error {
         Error::InternalError
@@ -1,48 +1,571 @@
 use assets::{Assets, Fonts};
 use cards::*;
 use error::{Error, Result};
-use unitypack::engine::texture::IntoTexture2D;
 use sfml::system::Vector2f;
 use sfml::graphics::{Color, Font, Image, RenderTarget, RenderTexture, Sprite, Text, TextStyle,
-                     Transformable};
-use builder;
-use utils::{ImageUtils, SpriteTransforms};
+                     Texture, Transformable};
+use builder::{self, scale_width, CARD_WIDTH, MIN_CARD_WIDTH, TILE_CARD_GEM_WIDTH,
+               TILE_COUNT_WIDTH, TILE_HEIGHT, TILE_WIDTH};
+use animation;
+use composition::{CardComposition, CompositionLayer};
+use deck::Deck;
+use layout::{CardLayout, Condition, Flip, Layer, LayerKind};
+use std::collections::HashMap;
+use std::io::{Seek, Write};
+use std::path::Path;
+use markup::{self, MarkupOptions};
+use utils::{ImageUtils, IntoImage};
+
+/// Options that change how a card is rendered
+#[derive(Debug, Default, Clone)]
+pub struct GenerateOptions {
+    /// Language of the card's name and text, missing translations fall back to enUS
+    pub locale: Locale,
+    /// Game state used to substitute values in the card text
+    pub markup: MarkupOptions,
+    /// Stats of the card in play, drawn instead of the base stats
+    pub current_stats: Option<CurrentStats>,
+    /// Golden cards are only supported for spells and minions
+    pub premium: Premium,
+}
+
+/// Stats of a card as it is in play, buffed values are drawn green and damaged or debuffed
+/// values red
+#[derive(Debug, Default, Clone)]
+pub struct CurrentStats {
+    pub cost: Option<i32>,
+    pub attack: Option<i32>,
+    pub health: Option<i32>,
+    pub durability: Option<i32>,
+    pub armor: Option<i32>,
+}
+
+/// Options of animated golden card exports
+#[derive(Debug, Clone)]
+pub struct AnimationOptions {
+    /// Number of frames in one loop of the animation
+    pub frame_count: u32,
+    /// Time between two frames in hundredths of a second
+    pub frame_delay: u16,
+}
 
-const CARD_ASPECT_RATIO: f32 = 360f32 / 510f32; //764f32 / 1100f32;
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        AnimationOptions {
+            frame_count: 24,
+            frame_delay: 5,
+        }
+    }
+}
+
+/// How the cards of a deck image are drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeckStyle {
+    /// Full cards with the number of copies below them
+    Cards,
+    /// Bars with the cost, name and a strip of the portrait, like the deck lists of the client
+    Tiles,
+}
+
+/// Options of deck images
+#[derive(Debug, Clone)]
+pub struct DeckImageOptions {
+    pub style: DeckStyle,
+    /// Cards in one row of the image
+    pub columns: usize,
+    /// Width of every card or tile in pixels
+    pub card_width: usize,
+    pub card: GenerateOptions,
+}
+
+impl Default for DeckImageOptions {
+    fn default() -> Self {
+        DeckImageOptions {
+            style: DeckStyle::Cards,
+            columns: 6,
+            card_width: 180,
+            card: GenerateOptions::default(),
+        }
+    }
+}
 
 pub struct Generator {
     assets: Assets,
     card_defs: CardDb,
+    layouts: HashMap<String, CardLayout>,
 }
 
 impl Generator {
+    /// Uses the card database embedded at build time
     pub fn new(assets_path: &str) -> Result<Self> {
-        let generator = Generator {
+        Generator::with_card_db(assets_path, CardDb::new()?)
+    }
+
+    pub fn with_card_db(assets_path: &str, card_db: CardDb) -> Result<Self> {
+        let mut generator = Generator {
             assets: Assets::new(assets_path)?,
-            card_defs: CardDb::new()?,
+            card_defs: card_db,
+            layouts: HashMap::new(),
         };
+        for layout in CardLayout::defaults()? {
+            generator.set_layout(layout);
+        }
 
         Ok(generator)
     }
 
+    pub fn card_db(&self) -> &CardDb {
+        &self.card_defs
+    }
+
+    /// Replaces the card database without reloading the assets
+    pub fn set_card_db(&mut self, card_db: CardDb) {
+        self.card_defs = card_db;
+    }
+
+    /// Replaces the card database with a cards.json file of any hearthstonejson build
+    pub fn load_card_db<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let card_db = CardDb::from_path(path)?;
+        self.set_card_db(card_db);
+        Ok(())
+    }
+
+    /// Renders the layout's card type with the given layout from now on
+    pub fn set_layout(&mut self, layout: CardLayout) {
+        self.layouts.insert(format!("{:?}", layout.card_type), layout);
+    }
+
+    /// Loads a layout from a JSON file, it replaces the layout of the same card type
+    pub fn load_layout<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let layout = CardLayout::from_path(path)?;
+        self.set_layout(layout);
+        Ok(())
+    }
+
     pub fn generate_card(&self, card_id: &str) -> Result<Image> {
-        self.generate_card_with_width(card_id, 360)
+        self.generate_card_with_options(card_id, &GenerateOptions::default())
+    }
+
+    /// Generates the card with its name and text in the given language
+    pub fn generate_card_localized(&self, card_id: &str, locale: Locale) -> Result<Image> {
+        let options = GenerateOptions {
+            locale: locale,
+            ..Default::default()
+        };
+        self.generate_card_with_options(card_id, &options)
+    }
+
+    pub fn generate_card_with_options(
+        &self,
+        card_id: &str,
+        options: &GenerateOptions,
+    ) -> Result<Image> {
+        self.generate_card_with_width(card_id, CARD_WIDTH, options)
+    }
+
+    /// Generates the card with the dbfId used by deck codes and game logs
+    pub fn generate_card_by_dbf_id(&self, dbf_id: u32, options: &GenerateOptions) -> Result<Image> {
+        let card = self.card_defs
+            .by_dbf_id(dbf_id)
+            .ok_or(Error::CardNotFoundError)?;
+        self.generate_card_with_options(&card.id, options)
+    }
+
+    /// Generates the card with the name in any locale, misspelled names are matched to the
+    /// closest card name
+    pub fn generate_card_by_name(&self, name: &str, options: &GenerateOptions) -> Result<Image> {
+        let card = self.card_defs
+            .find_by_name(name)
+            .ok_or(Error::CardNotFoundError)?;
+        self.generate_card_with_options(&card.id, options)
+    }
+
+    /// Renders one loop of the golden portrait animation, the options must ask for a golden card
+    pub fn generate_animated_card_frames(
+        &self,
+        card_id: &str,
+        options: &GenerateOptions,
+        animation: &AnimationOptions,
+    ) -> Result<Vec<Image>> {
+        if options.premium != Premium::Golden {
+            return Err(Error::NotImplementedError(format!(
+                "Only golden cards can be animated"
+            )));
+        }
+        if animation.frame_count == 0 {
+            return Err(Error::InvalidOptionsError(format!(
+                "Animations need at least one frame"
+            )));
+        }
+
+        // the card is composed once, only the effect layers change between frames
+        let mut composition = self.compose(card_id, CARD_WIDTH, options, Some(0f32))?;
+        let card = self.card_defs.get(card_id).ok_or(Error::CardNotFoundError)?;
+        let card_type = card.card_type.as_ref().ok_or(Error::InvalidCardError)?;
+        let effect_layers: Vec<&Layer> = self.layout(card_type)?
+            .layers
+            .iter()
+            .filter(|layer| layer.kind == LayerKind::PortraitEffect && layer_applies(layer, card))
+            .collect();
+
+        let mut frames = Vec::with_capacity(animation.frame_count as usize);
+        for frame in 0..animation.frame_count {
+            let phase = frame as f32 / animation.frame_count as f32;
+            if frame > 0 {
+                for layer in &effect_layers {
+                    let effect = self.build_portrait_effect(
+                        layer,
+                        card_class(card),
+                        options.premium,
+                        phase,
+                        1f32,
+                    )?;
+                    if let Some(composition_layer) = composition.layer_mut(&layer.name) {
+                        composition_layer.image =
+                            effect.texture().copy_to_image().ok_or(Error::SFMLError)?;
+                    }
+                }
+            }
+            frames.push(composition.flatten()?);
+        }
+        Ok(frames)
+    }
+
+    /// Renders the golden portrait animation and writes it as a looping GIF
+    pub fn generate_animated_card<W: Write>(
+        &self,
+        card_id: &str,
+        options: &GenerateOptions,
+        animation: &AnimationOptions,
+        writer: W,
+    ) -> Result<()> {
+        let frames = self.generate_animated_card_frames(card_id, options, animation)?;
+        animation::encode_gif(&frames, animation.frame_delay, writer)
+    }
+
+    /// Renders the card with the given width in pixels, the height follows from the card's
+    /// aspect ratio
+    pub fn generate_card_with_width(
+        &self,
+        card_id: &str,
+        card_width: usize,
+        options: &GenerateOptions,
+    ) -> Result<Image> {
+        self.compose(card_id, card_width, options, None)?.flatten()
+    }
+
+    /// Renders every layer of the card separately, they can be replaced or hidden before the
+    /// composition is flattened
+    pub fn compose_card(
+        &self,
+        card_id: &str,
+        options: &GenerateOptions,
+    ) -> Result<CardComposition> {
+        self.compose_card_with_width(card_id, CARD_WIDTH, options)
+    }
+
+    pub fn compose_card_with_width(
+        &self,
+        card_id: &str,
+        card_width: usize,
+        options: &GenerateOptions,
+    ) -> Result<CardComposition> {
+        self.compose(card_id, card_width, options, None)
+    }
+
+    /// Writes the layers of the card into an OpenRaster (.ora) file
+    pub fn generate_layered_card<W: Write + Seek>(
+        &self,
+        card_id: &str,
+        options: &GenerateOptions,
+        writer: W,
+    ) -> Result<()> {
+        self.compose_card(card_id, options)?.write_openraster(writer)
+    }
+
+    /// Renders a card that is not in the card database, the portrait is mapped like the
+    /// portrait textures of the game, so square images fit best
+    pub fn generate_custom_card(&self, def: &CustomCard, portrait: &Image) -> Result<Image> {
+        self.generate_custom_card_with_options(def, portrait, &GenerateOptions::default())
+    }
+
+    pub fn generate_custom_card_with_options(
+        &self,
+        def: &CustomCard,
+        portrait: &Image,
+        options: &GenerateOptions,
+    ) -> Result<Image> {
+        self.compose_custom_card(def, portrait, options)?.flatten()
+    }
+
+    pub fn compose_custom_card(
+        &self,
+        def: &CustomCard,
+        portrait: &Image,
+        options: &GenerateOptions,
+    ) -> Result<CardComposition> {
+        let size = portrait.size();
+        let mut portrait_img = Image::create_from_pixels(size.x, size.y, portrait.pixel_data())
+            .ok_or(Error::SFMLError)?;
+        portrait_img.remove_transparency();
+        self.compose_definition(&def.to_card(), &portrait_img, CARD_WIDTH, options, None)
+    }
+
+    /// Renders the cards of a deck code into one image, sorted like in the client, with the
+    /// number of copies of cards that are in the deck more than once
+    pub fn generate_deck_image(
+        &self,
+        deck_code: &str,
+        options: &DeckImageOptions,
+    ) -> Result<Image> {
+        let deck = Deck::from_code(deck_code)?;
+        let cards = deck.resolve(&self.card_defs, options.card.locale)?;
+        if cards.is_empty() {
+            return Err(Error::InvalidDeckCodeError(format!("The deck has no cards")));
+        }
+
+        let mut images = Vec::with_capacity(cards.len());
+        for &(card, copies) in &cards {
+            let image = match options.style {
+                DeckStyle::Cards => {
+                    self.generate_card_with_width(&card.id, options.card_width, &options.card)?
+                }
+                DeckStyle::Tiles => {
+                    self.generate_deck_tile(&card.id, copies, options.card_width, &options.card)?
+                }
+            };
+            images.push(image);
+        }
+
+        // cards of different types have different sizes, each is centered in a cell of the
+        // largest size
+        let card_size = images.iter().fold((0, 0), |(width, height), image| {
+            (width.max(image.size().x), height.max(image.size().y))
+        });
+        let scale = options.card_width as f32 / CARD_WIDTH as f32;
+        // tiles show the number of copies themselves
+        let count_height = match options.style {
+            DeckStyle::Cards => (60f32 * scale).ceil() as u32,
+            DeckStyle::Tiles => 0,
+        };
+        let cell_width = card_size.0;
+        let cell_height = card_size.1 + count_height;
+        let columns = options.columns.max(1).min(cards.len());
+        let rows = (cards.len() + columns - 1) / columns;
+
+        let mut canvas = RenderTexture::new(
+            cell_width * columns as u32,
+            cell_height * rows as u32,
+            false,
+        ).ok_or(Error::SFMLError)?;
+        canvas.clear(&builder::TRANSPARENT_COLOR);
+        canvas.set_smooth(true);
+
+        let belwe_raw = self.assets.get_font(&Fonts::Belwe)?;
+        let belwe = Font::from_memory(&belwe_raw.data).ok_or(Error::SFMLError)?;
+        let mut count_text = create_stat_text(&belwe, belwe_raw.pixel_scale, scale / 2f32);
+
+        for (index, (&(_, copies), image)) in cards.iter().zip(images.iter()).enumerate() {
+            let cell = Vector2f::new(
+                (index % columns) as f32 * cell_width as f32,
+                (index / columns) as f32 * cell_height as f32,
+            );
+
+            let mut texture = Texture::from_image(image).ok_or(Error::SFMLError)?;
+            texture.set_smooth(true);
+            let mut sprite = Sprite::with_texture(&texture);
+            sprite.set_position(Vector2f::new(
+                cell.x + (cell_width - image.size().x) as f32 / 2f32,
+                cell.y + (card_size.1 - image.size().y) as f32 / 2f32,
+            ));
+            canvas.draw(&sprite);
+
+            if copies > 1 && options.style == DeckStyle::Cards {
+                count_text.set_string(&format!("x{}", copies));
+                count_text.set_position(Vector2f::new(0f32, 0f32));
+                let bounds = count_text.global_bounds();
+                count_text.set_position(Vector2f::new(
+                    cell.x + (cell_width as f32 - bounds.width) / 2f32 - bounds.left,
+                    cell.y + card_size.1 as f32 + (count_height as f32 - bounds.height) / 2f32
+                        - bounds.top,
+                ));
+                canvas.draw(&count_text);
+            }
+        }
+
+        canvas.display();
+        Ok(canvas.texture().copy_to_image().ok_or(Error::SFMLError)?)
+    }
+
+    /// Renders the bar that stands for the card in deck lists, with the number of copies or the
+    /// star of legendary cards on its right side. The height follows from the width.
+    pub fn generate_deck_tile(
+        &self,
+        card_id: &str,
+        copies: u32,
+        tile_width: usize,
+        options: &GenerateOptions,
+    ) -> Result<Image> {
+        self.compose_deck_tile(card_id, copies, tile_width, options)?
+            .flatten()
     }
 
-    // This function is supposed to generate cards of arbitary size but not all subfunctions are ready for that yet
-    fn generate_card_with_width(&self, card_id: &str, card_width: usize) -> Result<Image> {
+    pub fn compose_deck_tile(
+        &self,
+        card_id: &str,
+        copies: u32,
+        tile_width: usize,
+        options: &GenerateOptions,
+    ) -> Result<CardComposition> {
+        let card = self.card_defs.get(card_id).ok_or(Error::CardNotFoundError)?;
+        if tile_width == 0 {
+            return Err(Error::InternalError);
+        }
+
+        // every position and size is measured on a TILE_WIDTH wide tile
+        let scale = tile_width as f32 / TILE_WIDTH as f32;
+        let width = tile_width as u32;
+        let height = ((TILE_HEIGHT as f32 * scale).round() as u32).max(1);
+        let mut composition = CardComposition::new(width, height);
+
+        let legendary = card.rarity == Some(CardRarity::LEGENDARY);
+        let with_count = copies > 1 || legendary;
+
+        let portrait_img = self.assets.get_card_portrait(card_id)?.to_sfml_image()?;
+        let background = builder::build_tile_background(&portrait_img, with_count, width, height)?;
+        composition.push_layer(CompositionLayer::new(
+            "background",
+            background.texture().copy_to_image().ok_or(Error::SFMLError)?,
+            Vector2f::new(0f32, 0f32),
+        ));
+
+        // the gem is as high as the tile, its number is scaled like on a card with a gem of the
+        // same size
+        let mana_gem = builder::build_mana_gem(
+            &self.assets.textures,
+            &self.assets.meshes,
+            options.premium,
+            scale_width(TILE_HEIGHT, scale),
+        )?;
+        let gem_scale = height as f32 / mana_gem.size().y as f32;
+        let gem_size = Vector2f::new(mana_gem.size().x as f32 * gem_scale, height as f32);
+        let mut mana_gem_layer = CompositionLayer::new(
+            "mana_gem",
+            mana_gem.texture().copy_to_image().ok_or(Error::SFMLError)?,
+            Vector2f::new(2f32 * scale, 0f32),
+        );
+        mana_gem_layer.transform.scale = Vector2f::new(gem_scale, gem_scale);
+        mana_gem_layer.transform.flip = Flip::Vertical;
+        composition.push_layer(mana_gem_layer);
+
+        let belwe_raw = self.assets.get_font(&Fonts::Belwe)?;
+        let belwe = Font::from_memory(&belwe_raw.data).ok_or(Error::SFMLError)?;
+        if let Some(cost) = card.cost {
+            let mut belwe_text =
+                create_stat_text(&belwe, belwe_raw.pixel_scale, gem_size.x / TILE_CARD_GEM_WIDTH);
+            let current_cost = options.current_stats.as_ref().and_then(|s| s.cost);
+            let (cost, color) = stat_display(cost, current_cost, true);
+            let (cost_texture, cost_position) = build_stat_value(
+                cost,
+                &color,
+                &mut belwe_text,
+                &Vector2f::new(2f32 * scale + gem_size.x / 2f32, gem_size.y / 2f32),
+            )?;
+            composition.push_layer(CompositionLayer::new(
+                "cost",
+                cost_texture.texture().copy_to_image().ok_or(Error::SFMLError)?,
+                cost_position,
+            ));
+        }
+
+        // long names are shrunk to end before the count box
+        let unknown_str = format!("Unknown");
+        let card_name: &str = match &card.name {
+            &Some(ref name) => name.localized(options.locale),
+            &None => &unknown_str,
+        };
+        let loaded_fonts = self.load_font_chain(Fonts::Belwe, options.locale)?;
+        let fonts = builder::FontChain::new(
+            loaded_fonts
+                .iter()
+                .map(|&(name, ref font)| (font, name))
+                .collect(),
+        )?;
+        let name_texture = builder::build_tile_name(card_name, &fonts, width)?;
+        let name_left = 4f32 * scale + gem_size.x;
+        let name_right = if with_count {
+            width as f32 - (TILE_COUNT_WIDTH + 4f32) * scale
+        } else {
+            width as f32 - 4f32 * scale
+        };
+        let name_size = name_texture.size();
+        let name_scale = ((name_right - name_left) / name_size.x as f32).min(1f32);
+        let mut name_layer = CompositionLayer::new(
+            "name",
+            name_texture.texture().copy_to_image().ok_or(Error::SFMLError)?,
+            Vector2f::new(
+                name_left,
+                (height as f32 - name_size.y as f32 * name_scale) / 2f32,
+            ),
+        );
+        name_layer.transform.scale = Vector2f::new(name_scale, name_scale);
+        composition.push_layer(name_layer);
+
+        if with_count {
+            let count =
+                builder::build_tile_count(copies, &belwe, belwe_raw.pixel_scale, width, height)?;
+            composition.push_layer(CompositionLayer::new(
+                "count",
+                count.texture().copy_to_image().ok_or(Error::SFMLError)?,
+                Vector2f::new(width as f32 - count.size().x as f32, 0f32),
+            ));
+        }
+
+        Ok(composition)
+    }
+
+    // The effect phase is only given for frames of animated golden cards
+    fn compose(
+        &self,
+        card_id: &str,
+        card_width: usize,
+        options: &GenerateOptions,
+        effect_phase: Option<f32>,
+    ) -> Result<CardComposition> {
         // obtain card data
-        let card = match self.card_defs.cards.get(card_id) {
+        let card = match self.card_defs.get(card_id) {
             Some(c) => c,
             None => {
                 return Err(Error::CardNotFoundError);
             }
         };
 
+        let mut portrait_img = self.assets.get_card_portrait(card_id)?.to_sfml_image()?;
+        portrait_img.remove_transparency();
+
+        self.compose_definition(card, &portrait_img, card_width, options, effect_phase)
+    }
+
+    // Builds the layers of a card from its definition, the portrait must be opaque
+    fn compose_definition(
+        &self,
+        card: &Card,
+        portrait_img: &Image,
+        card_width: usize,
+        options: &GenerateOptions,
+        effect_phase: Option<f32>,
+    ) -> Result<CardComposition> {
+        if card_width < MIN_CARD_WIDTH {
+            return Err(Error::InvalidOptionsError(format!(
+                "Cards must be at least {} pixels wide",
+                MIN_CARD_WIDTH
+            )));
+        }
+
         let unknown_str = "Unknown";
 
         let card_name: &str = match &card.name {
-            &Some(ref name) => &name.en_us,
+            &Some(ref name) => name.localized(options.locale),
             &None => &unknown_str,
         };
 
@@ -53,318 +576,362 @@ impl Generator {
             }
         };
 
-        let card_class = card.card_class.as_ref().ok_or(Error::InvalidCardError)?;
+        let layout = self.layout(card_type)?;
 
-        let card_height = (card_width as f32 / CARD_ASPECT_RATIO).ceil() as usize;
+        let premium = options.premium;
+        if premium == Premium::Golden && !layout.premium {
+            return Err(Error::NotImplementedError(format!(
+                "Golden {:?} cards are not implemented",
+                card_type
+            )));
+        }
 
-        // Create transparent canvas
-        let mut canvas = RenderTexture::new(
-            (card_width as f32 * 1.13) as u32,
-            (card_height as f32 * 1.13) as u32,
-            false,
-        ).ok_or(Error::SFMLError)?;
-        canvas.clear(&builder::TRANSPARENT_COLOR);
-        canvas.set_smooth(true);
+        let card_class = card_class(card);
 
-        // get card frame, TODO: do not add text background, it should come separate with expansion logo
-        let card_frame = self.assets.get_card_frame(card_type, card_class)?;
-        let mut frame_sprite = Sprite::with_texture(card_frame.texture());
-
-        // frame sprite accordingly
-        let scale_factor = card_width as f32 / card_frame.size().x as f32;
-        let card_frame_origin = Vector2f::new(28.25f32 * scale_factor, 60f32 * scale_factor);
-        frame_sprite.set_scale(Vector2f::new(scale_factor, scale_factor));
-        frame_sprite.set_position(Vector2f::new(card_frame_origin.x, card_frame_origin.y));
-
-        // draw card frame
-        canvas.draw(&frame_sprite);
-
-        // draw image portrait, TODO: these should be scale-dependent
-        self.draw_card_portrait(card_id, &card_type, &card_frame_origin, &mut canvas)?;
-        self.draw_portrait_frame(&card_type, &card_class, &card_frame_origin, &mut canvas)?;
-
-        // draw rarity gem
-        match card.rarity {
-            Some(ref rarity) => {
-                match rarity {
-                    &CardRarity::FREE => {}
-                    _ => {
-                        self.draw_rarity_gem(rarity, &card_frame_origin, &mut canvas)?;
-                    }
-                };
-            }
-            None => {}
-        };
+        // every position and size of the layout is measured on a CARD_WIDTH wide card
+        let scale = card_width as f32 / CARD_WIDTH as f32;
 
-        // draw name banner
-        self.draw_name_banner(&card_type, &card_frame_origin, &mut canvas)?;
+        let mut composition = CardComposition::new(
+            (layout.canvas.width * scale) as u32,
+            (layout.canvas.height * scale) as u32,
+        );
 
-        // draw mana gem
-        self.draw_mana_gem(&card_frame_origin, &mut canvas)?;
+        let card_origin = scaled(layout.origin.x, layout.origin.y, scale);
 
         let belwe_raw = self.assets.get_font(&Fonts::Belwe)?;
         let belwe = Font::from_memory(&belwe_raw.data).ok_or(Error::SFMLError)?;
+        let mut belwe_text = create_stat_text(&belwe, belwe_raw.pixel_scale, scale);
 
-        let mut belwe_text = Text::new("", &belwe, 87);
-        belwe_text.set_style(TextStyle::BOLD);
-        belwe_text.set_outline_color(&Color::BLACK);
-        belwe_text.set_outline_thickness(3f32);
-        belwe_text.scale(Vector2f::new(
-            1f32 + belwe_raw.pixel_scale,
-            1f32 + belwe_raw.pixel_scale,
-        ));
+        for layer in &layout.layers {
+            if !layer_applies(layer, card) {
+                continue;
+            }
+            // the elite dragon is decoration, cards are still drawn without its mesh
+            if layer.condition == Some(Condition::Elite) && !self.has_layer_mesh(layer) {
+                continue;
+            }
 
-        let mut mana_cost: i32 = -1;
-
-        // draw mana cost
-        match card.cost {
-            Some(cost) => {
-                match card.hide_stats {
-                    Some(hide_stats) => {
-                        if !hide_stats {
-                            mana_cost = cost;
-                        } else {
-                            mana_cost = -1;
-                        }
+            let mut position = scaled(layer.position.x, layer.position.y, scale) + card_origin;
+            let width = scale_width(layer.width, scale);
+            let layer_texture = match layer.kind {
+                LayerKind::Image => {
+                    let texture_name = self.layer_texture_name(layer, card_class, premium)?;
+                    Some(builder::build_layer(
+                        &self.assets.textures,
+                        &self.assets.meshes,
+                        layer,
+                        &texture_name,
+                        width,
+                    )?)
+                }
+                LayerKind::Portrait => Some(builder::build_image_layer(
+                    portrait_img,
+                    &self.assets.meshes,
+                    layer,
+                    width,
+                )?),
+                LayerKind::PortraitEffect => match (premium, effect_phase) {
+                    (Premium::Golden, Some(phase)) => Some(self.build_portrait_effect(
+                        layer,
+                        card_class,
+                        premium,
+                        phase,
+                        scale,
+                    )?),
+                    _ => None,
+                },
+                LayerKind::RarityGem => match card.rarity {
+                    Some(ref rarity) => Some(builder::build_rarity_gem(
+                        &self.assets.textures,
+                        &self.assets.meshes,
+                        layer,
+                        rarity,
+                        width,
+                    )?),
+                    None => None,
+                },
+                LayerKind::Name => {
+                    Some(self.build_card_name(card_name, layer, options.locale, scale)?)
+                }
+                LayerKind::Text => match card.text {
+                    Some(ref text) => {
+                        let card_text =
+                            markup::parse(text.localized(options.locale), &options.markup);
+                        self.build_card_text(&card_text, layer, options.locale, scale)?
                     }
-                    None => {
-                        mana_cost = cost;
+                    None => None,
+                },
+                _ => {
+                    // numbers are centered at the position of the layer
+                    let current_stats = options.current_stats.as_ref();
+                    match layer_stat(layer, card, current_stats) {
+                        Some((value, color)) => {
+                            let (texture, top_left) =
+                                build_stat_value(value, &color, &mut belwe_text, &position)?;
+                            position = top_left;
+                            Some(texture)
+                        }
+                        None => None,
                     }
-                };
+                }
+            };
+
+            if let Some(texture) = layer_texture {
+                let image = texture.texture().copy_to_image().ok_or(Error::SFMLError)?;
+                let mut composition_layer = CompositionLayer::new(&layer.name, image, position);
+                composition_layer.transform.flip = layer.flip;
+                composition_layer.blend = layer.blend;
+                composition.push_layer(composition_layer);
             }
-            None => {}
-        };
-        if mana_cost >= 0 {
-            belwe_text.set_string(&mana_cost.to_string());
-            let bounds = belwe_text.global_bounds();
-            belwe_text.set_position(Vector2f::new(
-                card_frame_origin.x - (bounds.width / 2f32) - bounds.left + 31.5f32,
-                card_frame_origin.y - (bounds.height / 2f32) - bounds.top + 23f32,
-            ));
-            canvas.draw(&belwe_text);
         }
 
-        // draw card's name
-        self.draw_card_name(card_name, &mut belwe_text, &card_frame_origin, &mut canvas)?;
-
-        // render off screen
-        canvas.display();
-        Ok(canvas.texture().copy_to_image().ok_or(Error::SFMLError)?)
+        Ok(composition)
     }
 
-    fn draw_card_portrait(
+    fn build_portrait_effect(
         &self,
-        card_id: &str,
-        card_type: &CardType,
-        frame_origin: &Vector2f,
-        canvas: &mut RenderTexture,
-    ) -> Result<()> {
-        let portrait = self.assets.get_card_portrait(card_id)?;
-        let width = portrait.width;
-        let height = portrait.height;
-        let mut portrait_img = Image::create_from_pixels(width, height, &portrait.to_image()?)
-            .ok_or(Error::SFMLError)?;
+        layer: &Layer,
+        card_class: &CardClass,
+        premium: Premium,
+        phase: f32,
+        scale: f32,
+    ) -> Result<RenderTexture> {
+        let texture_name = self.layer_texture_name(layer, card_class, premium)?;
+        builder::build_portrait_effect(
+            &self.assets.textures,
+            &self.assets.meshes,
+            layer,
+            &texture_name,
+            phase,
+            scale_width(layer.width, scale),
+        )
+    }
 
-        portrait_img.remove_transparency();
+    fn has_layer_mesh(&self, layer: &Layer) -> bool {
+        match layer.mesh {
+            Some(ref mesh) => self.assets.meshes.contains_key(mesh),
+            None => false,
+        }
+    }
 
-        match *card_type {
-            CardType::Spell => {
-                // draw portrait with shadow
-                let portrait_texture = builder::build_ability_portrait(
-                    &portrait_img,
-                    &self.assets.textures,
-                    &self.assets.meshes,
-                )?;
-                let mut portrait_sprite = Sprite::with_texture(&portrait_texture.texture());
-
-                let portrait_position = Vector2f {
-                    x: 36f32 + frame_origin.x,
-                    y: 32f32 + frame_origin.y,
-                };
-                portrait_sprite.set_position(portrait_position);
-                canvas.draw(&portrait_sprite);
-            }
-            _ => {
-                return Err(Error::NotImplementedError(format!(
-                    "Card type {:?} is not yet implemented",
-                    card_type
-                )));
-            }
-        };
-        Ok(())
+    fn layout(&self, card_type: &CardType) -> Result<&CardLayout> {
+        self.layouts
+            .get(&format!("{:?}", card_type))
+            .ok_or(Error::NotImplementedError(format!(
+                "Card type {:?} has no layout",
+                card_type
+            )))
     }
 
-    fn draw_portrait_frame(
+    // Texture of an image layer for the card's class, golden cards use the premium variant
+    fn layer_texture_name(
         &self,
-        card_type: &CardType,
+        layer: &Layer,
         card_class: &CardClass,
-        frame_origin: &Vector2f,
-        canvas: &mut RenderTexture,
-    ) -> Result<()> {
-        match *card_type {
-            CardType::Spell => {
-                let card_frame = match *card_class {
-                    CardClass::Mage => {
-                        Assets::catalog_get(&self.assets.textures, "Card_Inhand_Ability_Mage")?
-                            .to_texture2d()?
-                    }
-                    CardClass::Priest => {
-                        Assets::catalog_get(&self.assets.textures, "Card_Inhand_Ability_Priest")?
-                            .to_texture2d()?
-                    }
-                    CardClass::Hunter => {
-                        Assets::catalog_get(&self.assets.textures, "Card_Inhand_Ability_Hunter")?
-                            .to_texture2d()?
-                    }
-                    CardClass::Warlock => {
-                        Assets::catalog_get(&self.assets.textures, "Card_Inhand_Ability_Warlock")?
-                            .to_texture2d()?
-                    }
-                    CardClass::Shaman => {
-                        Assets::catalog_get(&self.assets.textures, "Card_Inhand_Ability_Shaman")?
-                            .to_texture2d()?
-                    }
-                    CardClass::Druid => {
-                        Assets::catalog_get(&self.assets.textures, "Card_Inhand_Ability_Druid")?
-                            .to_texture2d()?
-                    }
-                    CardClass::Paladin => {
-                        Assets::catalog_get(&self.assets.textures, "Card_Inhand_Ability_Paladin")?
-                            .to_texture2d()?
-                    }
-                    CardClass::Rogue => {
-                        Assets::catalog_get(&self.assets.textures, "Card_Inhand_Ability_Rogue")?
-                            .to_texture2d()?
-                    }
-                    CardClass::Warrior => {
-                        Assets::catalog_get(&self.assets.textures, "Card_Inhand_Ability_Warrior")?
-                            .to_texture2d()?
-                    }
-                    _ => {
-                        return Err(Error::NotImplementedError(format!(
-                            "Card class {:?} for portrait frame is not yet implemented",
-                            card_class
-                        )));
-                    }
-                };
-                let card_frame_image = Image::create_from_pixels(
-                    card_frame.width,
-                    card_frame.height,
-                    &card_frame.to_image()?,
-                ).ok_or(Error::SFMLError)?;
-                let portrait_frame_texture =
-                    builder::build_ability_portrait_frame(&card_frame_image, &self.assets.meshes)?;
-                let mut portrait_frame_sprite =
-                    Sprite::with_texture(&portrait_frame_texture.texture());
-                portrait_frame_sprite.flip_vertically();
-
-                let portrait_frame_sprite_position = Vector2f {
-                    x: 25f32 + frame_origin.x,
-                    y: 20f32 + frame_origin.y,
-                };
-                portrait_frame_sprite.set_position(portrait_frame_sprite_position);
-                canvas.draw(&portrait_frame_sprite);
-            }
-            _ => {
-                return Err(Error::NotImplementedError(format!(
-                    "Card type {:?} is not yet implemented",
-                    card_type
-                )));
-            }
-        };
-        Ok(())
+        premium: Premium,
+    ) -> Result<String> {
+        let texture_name = layer
+            .class_texture(card_class, |name| self.assets.textures.contains_key(name))
+            .ok_or(Error::InvalidAssetError(format!(
+                "Layer {} has no texture",
+                layer.name
+            )))?;
+        Ok(if layer.premium {
+            premium.texture_name(&texture_name)
+        } else {
+            texture_name
+        })
     }
 
-    fn draw_name_banner(
+    // Returns None for cards without text
+    fn build_card_text(
         &self,
-        card_type: &CardType,
-        frame_origin: &Vector2f,
-        canvas: &mut RenderTexture,
-    ) -> Result<()> {
-        match *card_type {
-            CardType::Spell => {
-                let banner_texture = builder::build_ability_name_banner(
-                    &self.assets.textures,
-                    &self.assets.meshes,
-                    346,
-                )?;
-                let mut banner_sprite = Sprite::with_texture(&banner_texture.texture());
-                banner_sprite.flip_vertically();
-                banner_sprite.set_position(Vector2f {
-                    x: 6f32 + frame_origin.x,
-                    y: 221f32 + frame_origin.y,
-                });
-                canvas.draw(&banner_sprite);
-            }
-            _ => {
-                return Err(Error::NotImplementedError(format!(
-                    "Card type {:?} is not yet implemented",
-                    card_type
-                )));
-            }
+        card_text: &markup::CardText,
+        layer: &Layer,
+        locale: Locale,
+        scale: f32,
+    ) -> Result<Option<RenderTexture>> {
+        let plain_text = card_text.plain_text();
+        if plain_text.trim().is_empty() {
+            return Ok(None);
+        }
+
+        let loaded_fonts = self.load_font_chain(Fonts::FranklinGothic, locale)?;
+        let fonts = builder::FontChain::new(
+            loaded_fonts
+                .iter()
+                .map(|&(name, ref font)| (font, name))
+                .collect(),
+        )?;
+
+        let color = match layer.color {
+            Some([r, g, b]) => Color::rgb(r, g, b),
+            None => Color::BLACK,
         };
-        Ok(())
+        let text_texture = builder::build_card_text(
+            card_text,
+            &fonts,
+            &color,
+            scale_width(layer.width, scale) as u32,
+            scale_width(layer.height, scale) as u32,
+            scale,
+        )?;
+        Ok(Some(text_texture))
     }
 
-    fn draw_mana_gem(&self, frame_origin: &Vector2f, canvas: &mut RenderTexture) -> Result<()> {
-        let mana_gem = builder::build_mana_gem(&self.assets.textures, &self.assets.meshes, 94)?;
-        let mut mana_gem_sprite = Sprite::with_texture(&mana_gem.texture());
-        mana_gem_sprite.flip_horizontally();
-        mana_gem_sprite.set_position(Vector2f {
-            x: frame_origin.x - 13f32,
-            y: frame_origin.y - 20f32,
-        });
-        canvas.draw(&mana_gem_sprite);
-        Ok(())
-    }
+    // Loads the fonts used for every character of a text in order of preference: the locale's
+    // own font, the given font, then the global font covering most scripts
+    fn load_font_chain(&self, primary: Fonts, locale: Locale) -> Result<Vec<(Fonts, Font)>> {
+        let localized = match primary {
+            Fonts::Belwe | Fonts::BelweOutline => Fonts::LocalizedTitle(locale),
+            _ => Fonts::LocalizedBody(locale),
+        };
 
-    fn draw_rarity_gem(
-        &self,
-        rarity: &CardRarity,
-        frame_origin: &Vector2f,
-        canvas: &mut RenderTexture,
-    ) -> Result<()> {
-        // draw socket
-        let rarity_gem_socket =
-            builder::build_rarity_gem_socket(&self.assets.textures, &self.assets.meshes, 66)?;
-        let mut rarity_gem_socket_sprite = Sprite::with_texture(&rarity_gem_socket.texture());
-        rarity_gem_socket_sprite.flip_vertically();
-        rarity_gem_socket_sprite.set_position(Vector2f {
-            x: 143f32 + frame_origin.x,
-            y: 279f32 + frame_origin.y,
-        });
-        canvas.draw(&rarity_gem_socket_sprite);
-
-        // draw gem
-        let rarity_gem =
-            builder::build_rarity_gem(&self.assets.textures, &self.assets.meshes, rarity, 29)?;
-        let mut rarity_gem_sprite = Sprite::with_texture(&rarity_gem.texture());
-        rarity_gem_sprite.flip_vertically();
-        rarity_gem_sprite.set_position(Vector2f {
-            x: 163f32 + frame_origin.x,
-            y: 291f32 + frame_origin.y,
-        });
-        canvas.draw(&rarity_gem_sprite);
-        Ok(())
+        let mut fonts = Vec::new();
+        for name in vec![localized, primary, Fonts::BlizzardGlobal] {
+            if !self.assets.has_font(&name) {
+                continue;
+            }
+            let font_raw = self.assets.get_font(&name)?;
+            let font = Font::from_memory(&font_raw.data).ok_or(Error::SFMLError)?;
+            fonts.push((name, font));
+        }
+
+        if fonts.is_empty() {
+            return Err(Error::AssetNotFoundError(format!(
+                "Cannot find font named {:?}",
+                primary
+            )));
+        }
+        Ok(fonts)
     }
 
-    fn draw_card_name(
+    fn build_card_name(
         &self,
         card_name: &str,
-        text: &mut Text,
-        frame_origin: &Vector2f,
-        canvas: &mut RenderTexture,
-    ) -> Result<()> {
-        let name_texture = builder::build_name_texture(card_name, text)?;
-
-        let card_name = builder::build_card_name(name_texture.texture(), &self.assets.meshes, 318)?;
-        let mut card_name_sprite = Sprite::with_texture(&card_name.texture());
-        card_name_sprite.flip_vertically();
-        card_name_sprite.set_position(Vector2f {
-            x: 20f32 + frame_origin.x,
-            y: 226f32 + frame_origin.y,
-        });
-        canvas.draw(&card_name_sprite);
-        Ok(())
+        layer: &Layer,
+        locale: Locale,
+        scale: f32,
+    ) -> Result<RenderTexture> {
+        let loaded_fonts = self.load_font_chain(Fonts::Belwe, locale)?;
+        let fonts = builder::FontChain::new(
+            loaded_fonts
+                .iter()
+                .map(|&(name, ref font)| (font, name))
+                .collect(),
+        )?;
+        let name_texture = builder::build_name_texture(card_name, &fonts, scale)?;
+
+        builder::build_card_name(
+            name_texture.texture(),
+            &self.assets.meshes,
+            layer,
+            scale_width(layer.width, scale),
+        )
+    }
+}
+
+// Renders a number on its own texture, returns it with the position that centers it on center
+fn build_stat_value(
+    value: i32,
+    color: &Color,
+    text: &mut Text,
+    center: &Vector2f,
+) -> Result<(RenderTexture, Vector2f)> {
+    text.set_string(&value.to_string());
+    text.set_fill_color(color);
+    text.set_position(Vector2f::new(0f32, 0f32));
+    let bounds = text.global_bounds();
+
+    let mut canvas = RenderTexture::new(
+        bounds.width.ceil() as u32,
+        bounds.height.ceil() as u32,
+        false,
+    ).ok_or(Error::SFMLError)?;
+    canvas.clear(&builder::TRANSPARENT_COLOR);
+    canvas.set_smooth(true);
+    text.set_position(Vector2f::new(-bounds.left, -bounds.top));
+    canvas.draw(text);
+    canvas.display();
+
+    let position = Vector2f::new(
+        center.x - (bounds.width / 2f32),
+        center.y - (bounds.height / 2f32),
+    );
+    Ok((canvas, position))
+}
+
+// Classless cards and classes without textures use the neutral ones
+fn card_class(card: &Card) -> &CardClass {
+    match card.card_class {
+        Some(CardClass::Unknown(_)) | None => &CardClass::Neutral,
+        Some(ref card_class) => card_class,
+    }
+}
+
+fn layer_applies(layer: &Layer, card: &Card) -> bool {
+    match layer.condition {
+        Some(Condition::Elite) => card.is_elite(),
+        Some(Condition::Rarity) => match card.rarity {
+            Some(CardRarity::FREE) | Some(CardRarity::Unknown(_)) | None => false,
+            Some(_) => true,
+        },
+        None => true,
+    }
+}
+
+// Value and color of the number drawn by a stat layer, hidden stats are not drawn
+fn layer_stat(
+    layer: &Layer,
+    card: &Card,
+    current: Option<&CurrentStats>,
+) -> Option<(i32, Color)> {
+    if card.hide_stats.unwrap_or(false) {
+        return None;
     }
+    let (base, current_value, lower_is_better) = match layer.kind {
+        LayerKind::Cost => (card.cost, current.and_then(|s| s.cost), true),
+        LayerKind::Attack => (card.attack, current.and_then(|s| s.attack), false),
+        LayerKind::Health => (card.health, current.and_then(|s| s.health), false),
+        LayerKind::Durability => (card.durability, current.and_then(|s| s.durability), false),
+        LayerKind::Armor => (card.armor, current.and_then(|s| s.armor), false),
+        _ => {
+            return None;
+        }
+    };
+    base.map(|base| stat_display(base, current_value, lower_is_better))
+}
+
+// Text used to draw numbers (cost, attack, health etc.)
+fn create_stat_text<'a>(font: &'a Font, pixel_scale: f32, scale: f32) -> Text<'a> {
+    let mut text = Text::new("", font, ((87f32 * scale).round() as u32).max(1));
+    text.set_style(TextStyle::BOLD);
+    text.set_outline_color(&Color::BLACK);
+    text.set_outline_thickness(3f32 * scale);
+    text.scale(Vector2f::new(1f32 + pixel_scale, 1f32 + pixel_scale));
+    text
+}
+
+// Value to draw for a stat and its color: green if the current value is better than the base
+// value, red if it is worse
+fn stat_display(base: i32, current: Option<i32>, lower_is_better: bool) -> (i32, Color) {
+    let value = current.unwrap_or(base);
+    let improved = if lower_is_better {
+        value < base
+    } else {
+        value > base
+    };
+    let color = if value == base {
+        Color::WHITE
+    } else if improved {
+        Color::GREEN
+    } else {
+        Color::RED
+    };
+    (value, color)
+}
+
+// Position measured on a CARD_WIDTH wide card
+fn scaled(x: f32, y: f32, scale: f32) -> Vector2f {
+    Vector2f::new(x * scale, y * scale)
 }
@@ -0,0 +1,318 @@
+//! Declarative description of how a card type is put together
+//!
+//! A layout lists the layers of a card in drawing order. Positions and sizes are measured on a
+//! card that is `CARD_WIDTH` pixels wide, they are scaled to the requested output width.
+//! Default layouts are stored as JSON in `res/layouts`, other layouts can be loaded at runtime.
+
+use cards::{CardClass, CardType};
+use error::Result;
+use serde_json;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+const SPELL_LAYOUT: &'static str = include_str!("../res/layouts/spell.json");
+const MINION_LAYOUT: &'static str = include_str!("../res/layouts/minion.json");
+const WEAPON_LAYOUT: &'static str = include_str!("../res/layouts/weapon.json");
+const HERO_LAYOUT: &'static str = include_str!("../res/layouts/hero.json");
+const ENCHANTMENT_LAYOUT: &'static str = include_str!("../res/layouts/enchantment.json");
+const HERO_POWER_LAYOUT: &'static str = include_str!("../res/layouts/hero_power.json");
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct Position {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct Size {
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LayerKind {
+    /// Texture projected through a mesh
+    Image,
+    /// The card's portrait projected through a mesh
+    Portrait,
+    /// Scrolling effect over golden portraits, only drawn for animation frames
+    PortraitEffect,
+    /// Gem colored by the card's rarity
+    RarityGem,
+    /// The card's name, projected through a mesh to curve it
+    Name,
+    /// The card's rules text wrapped into a box
+    Text,
+    /// Numbers centered at the position of the layer
+    Cost,
+    Attack,
+    Health,
+    Durability,
+    Armor,
+}
+
+/// Layers drawn only for some cards
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    /// Legendary and elite cards
+    Elite,
+    /// Cards with a rarity gem, every rarity but free
+    Rarity,
+}
+
+/// Mirroring of the rendered layer
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Flip {
+    None,
+    Horizontal,
+    Vertical,
+}
+
+impl Default for Flip {
+    fn default() -> Self {
+        Flip::None
+    }
+}
+
+/// How the layer is combined with the layers below it
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Blend {
+    Alpha,
+    Add,
+    Multiply,
+}
+
+impl Default for Blend {
+    fn default() -> Self {
+        Blend::Alpha
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Layer {
+    pub name: String,
+    pub kind: LayerKind,
+    #[serde(default)]
+    pub condition: Option<Condition>,
+    #[serde(default)]
+    pub mesh: Option<String>,
+    #[serde(default)]
+    pub submesh: usize,
+    /// Texture of image layers, "{class}" is replaced by the class of the card
+    #[serde(default)]
+    pub texture: Option<String>,
+    /// Used when the texture of the card's class does not exist
+    #[serde(default)]
+    pub fallback_texture: Option<String>,
+    /// Golden cards use the "_Premium" variant of the texture
+    #[serde(default)]
+    pub premium: bool,
+    /// Texture coordinate channel of the mesh
+    #[serde(default = "default_channel")]
+    pub channel: usize,
+    /// Top left corner of the layer, or the center of numbers
+    #[serde(default)]
+    pub position: Position,
+    #[serde(default)]
+    pub width: usize,
+    /// Only used by text layers
+    #[serde(default)]
+    pub height: usize,
+    #[serde(default)]
+    pub flip: Flip,
+    #[serde(default)]
+    pub blend: Blend,
+    /// Draw the triangles of the mesh from back to front
+    #[serde(default)]
+    pub sort_by_z: bool,
+    /// Ignore the transparency of the texture
+    #[serde(default)]
+    pub opaque: bool,
+    /// Color of text layers, black by default
+    #[serde(default)]
+    pub color: Option<[u8; 3]>,
+}
+
+impl Layer {
+    /// Texture for a card of the given class, the fallback replaces class textures that do not
+    /// exist
+    pub fn class_texture<F>(&self, card_class: &CardClass, exists: F) -> Option<String>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let texture = match self.texture {
+            Some(ref texture) => texture.replace("{class}", &format!("{:?}", card_class)),
+            None => return None,
+        };
+        match self.fallback_texture {
+            Some(ref fallback) if !exists(&texture) => Some(fallback.clone()),
+            _ => Some(texture),
+        }
+    }
+}
+
+fn default_channel() -> usize {
+    3
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CardLayout {
+    pub card_type: CardType,
+    /// Golden cards can be rendered with this layout
+    #[serde(default)]
+    pub premium: bool,
+    /// Size of the whole image, it is larger than the card to fit the gems sticking out
+    pub canvas: Size,
+    /// Position of the card on the canvas, layer positions are relative to it
+    pub origin: Position,
+    pub layers: Vec<Layer>,
+}
+
+impl CardLayout {
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut json = String::new();
+        File::open(path)?.read_to_string(&mut json)?;
+        CardLayout::from_json(&json)
+    }
+
+    /// Layer with the given name
+    pub fn layer(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    /// Layouts of every card type
+    pub fn defaults() -> Result<Vec<CardLayout>> {
+        vec![
+            SPELL_LAYOUT,
+            MINION_LAYOUT,
+            WEAPON_LAYOUT,
+            HERO_LAYOUT,
+            ENCHANTMENT_LAYOUT,
+            HERO_POWER_LAYOUT,
+        ].into_iter()
+            .map(CardLayout::from_json)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use builder::{scale_width, CARD_WIDTH};
+
+    #[test]
+    fn parse_default_layouts() {
+        let layouts = CardLayout::defaults().unwrap();
+        assert_eq!(layouts.len(), 6);
+        let spell = &layouts[0];
+        assert!(spell.premium);
+        assert_eq!(spell.origin.x, 28.25);
+        let textbox = spell.layers.iter().find(|l| l.name == "textbox").unwrap();
+        assert_eq!(textbox.kind, LayerKind::Image);
+        assert_eq!(textbox.channel, 3);
+        assert_eq!(textbox.position.x, 41f32);
+        assert_eq!(textbox.flip, Flip::Horizontal);
+    }
+
+    #[test]
+    fn legendary_frames_have_elite_layers() {
+        for layout in CardLayout::defaults().unwrap() {
+            match layout.card_type {
+                CardType::Enchantment | CardType::HeroPower => continue,
+                _ => {}
+            }
+            let elite = layout.layers.iter().find(|l| l.name == "elite").unwrap();
+            assert_eq!(elite.condition, Some(Condition::Elite));
+        }
+    }
+
+    #[test]
+    fn thumbnail_layers_keep_their_size() {
+        // 128 pixel wide thumbnails
+        let scale = 128f32 / CARD_WIDTH as f32;
+        for layout in CardLayout::defaults().unwrap() {
+            assert!((layout.canvas.width * scale) as u32 > 0);
+            for layer in &layout.layers {
+                assert_eq!(scale_width(layer.width, scale) == 0, layer.width == 0);
+                assert_eq!(scale_width(layer.height, scale) == 0, layer.height == 0);
+            }
+        }
+        assert_eq!(scale_width(1, scale), 1);
+    }
+
+    #[test]
+    fn hero_power_portrait_is_drawn_inside_the_frame() {
+        let layouts = CardLayout::defaults().unwrap();
+        let hero_power = &layouts[5];
+        assert_eq!(hero_power.card_type, CardType::HeroPower);
+        let names: Vec<&str> = hero_power.layers.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, vec!["portrait", "frame", "mana_gem", "cost"]);
+        let portrait = hero_power.layer("portrait").unwrap();
+        let frame = hero_power.layer("frame").unwrap();
+        assert!(portrait.position.x > frame.position.x);
+        let portrait_right = portrait.position.x + portrait.width as f32;
+        assert!(portrait_right <= frame.position.x + frame.width as f32);
+    }
+
+    #[test]
+    fn enchantments_have_text() {
+        let layouts = CardLayout::defaults().unwrap();
+        let enchantment = &layouts[4];
+        assert_eq!(enchantment.card_type, CardType::Enchantment);
+        let text = enchantment.layer("text").unwrap();
+        assert_eq!(text.kind, LayerKind::Text);
+        assert!(text.position.y + text.height as f32 <= enchantment.canvas.height);
+    }
+
+    #[test]
+    fn class_textures_fall_back() {
+        let layouts = CardLayout::defaults().unwrap();
+        let spell = &layouts[0];
+        let frame = spell.layer("frame").unwrap();
+        let exists = |name: &str| name != "Card_Inhand_Ability_Neutral";
+        assert_eq!(
+            frame.class_texture(&CardClass::Mage, &exists).unwrap(),
+            "Card_Inhand_Ability_Mage"
+        );
+        assert_eq!(
+            frame.class_texture(&CardClass::Neutral, &exists).unwrap(),
+            "Card_Inhand_Ability_Warlock"
+        );
+
+        for layout in &layouts {
+            for layer in &layout.layers {
+                let class_texture = layer.texture.as_ref().map_or(false, |t| t.contains("{class}"));
+                assert!(!class_texture || layer.fallback_texture.is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn parse_layer_defaults() {
+        let layout = CardLayout::from_json(
+            r#"{
+                "card_type": "WEAPON",
+                "canvas": { "width": 100, "height": 200 },
+                "origin": { "x": 0, "y": 0 },
+                "layers": [{ "name": "attack", "kind": "attack" }]
+            }"#,
+        ).unwrap();
+        assert!(!layout.premium);
+        let layer = &layout.layers[0];
+        assert_eq!(layer.kind, LayerKind::Attack);
+        assert_eq!(layer.condition, None);
+        assert_eq!(layer.flip, Flip::None);
+        assert_eq!(layer.blend, Blend::Alpha);
+        assert_eq!(layer.submesh, 0);
+        assert!(!layer.sort_by_z);
+    }
+}
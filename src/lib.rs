@@ -1,22 +1,36 @@
+extern crate base64;
 extern crate byteorder;
+extern crate gif;
 extern crate glob;
 #[macro_use]
 extern crate lazy_static;
+extern crate png;
 extern crate rayon;
+extern crate serde;
 extern crate serde_json;
 extern crate sfml;
 extern crate time;
 extern crate unitypack;
+extern crate zip;
 
 #[macro_use]
 extern crate serde_derive;
 
 mod utils;
+mod animation;
+mod openraster;
 pub mod error;
 pub mod generator;
 mod builder;
 mod assets;
 mod cards;
+pub mod markup;
+pub mod layout;
+pub mod composition;
+pub mod deck;
+
+pub use cards::{Card, CardClass, CardDb, CardQuery, CardRarity, CardSet, CardType, CustomCard,
+                Locale, Mechanic, MultiClassGroup, Name, Premium, Race, SpellSchool};
 
 #[cfg(test)]
 mod tests {
@@ -51,4 +65,21 @@ mod tests {
         path.push_str("/Downloads/test.png");
         card_image.save_to_file(&path);
     }
+
+    #[test]
+    fn generate_thumbnails() {
+        let generator = Generator::new("/Applications/Hearthstone/Data/OSX/").unwrap();
+        let options = GenerateOptions::default();
+
+        let thumbnail = generator
+            .generate_card_with_width(CARD_ID_ICE_BARRIER, 128, &options)
+            .unwrap();
+        assert_eq!(thumbnail.size().x, 128);
+
+        match generator.generate_card_with_width(CARD_ID_ICE_BARRIER, 16, &options) {
+            Err(::error::Error::InvalidOptionsError(_)) => {}
+            Err(error) => panic!("Unexpected error {:?}", error),
+            Ok(_) => panic!("Cards narrower than the minimum must be rejected"),
+        }
+    }
 }
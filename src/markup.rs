@@ -0,0 +1,211 @@
+//! Parser for the markup used in card texts
+//!
+//! Supported syntax:
+//! - `<b>`, `</b>`, `<i>`, `</i>`: bold and italic text
+//! - `$N`: damage affected by spell damage
+//! - `#N`: healing affected by healing bonuses
+//! - `[x]`: text is already broken into lines, it must not be wrapped
+//! - `@`: separates the text with `{N}` placeholders from its plain version
+//! - `{N}`: placeholders filled in from the game state
+
+/// Game state dependent values used while parsing card texts
+#[derive(Debug, Default, Clone)]
+pub struct MarkupOptions {
+    /// Added to every `$N` damage value
+    pub spell_damage: i32,
+    /// Added to every `#N` healing value
+    pub healing_bonus: i32,
+    /// Values for the `{N}` placeholders, the placeholder version of the text is only used
+    /// when these are given
+    pub placeholders: Vec<String>,
+}
+
+/// A piece of text sharing the same style
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextRun {
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+}
+
+/// Card text split into styled runs, line breaks are kept as `\n` in the runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct CardText {
+    pub runs: Vec<TextRun>,
+    /// The text was broken into lines by hand and must not be wrapped
+    pub manual_breaks: bool,
+}
+
+impl CardText {
+    pub fn plain_text(&self) -> String {
+        self.runs.iter().map(|run| run.text.as_str()).collect()
+    }
+}
+
+pub fn parse(text: &str, options: &MarkupOptions) -> CardText {
+    let (text, manual_breaks) = if text.starts_with("[x]") {
+        (&text[3..], true)
+    } else {
+        (text, false)
+    };
+
+    let text = select_variant(text, options);
+
+    let mut runs: Vec<TextRun> = Vec::new();
+    let mut current = String::new();
+    let mut bold = false;
+    let mut italic = false;
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '<' => {
+                let tag: String = chars[i..].iter().take_while(|&&c| c != '>').collect();
+                let style = match tag.as_str() {
+                    "<b" => Some((true, italic)),
+                    "</b" => Some((false, italic)),
+                    "<i" => Some((bold, true)),
+                    "</i" => Some((bold, false)),
+                    _ => None,
+                };
+                match style {
+                    Some((new_bold, new_italic)) => {
+                        push_run(&mut runs, &mut current, bold, italic);
+                        bold = new_bold;
+                        italic = new_italic;
+                        i += tag.chars().count() + 1;
+                        continue;
+                    }
+                    None => current.push(c),
+                }
+            }
+            '$' | '#' => {
+                let digits: String = chars[i + 1..]
+                    .iter()
+                    .take_while(|c| c.is_digit(10))
+                    .collect();
+                match digits.parse::<i32>() {
+                    Ok(value) => {
+                        let bonus = if c == '$' {
+                            options.spell_damage
+                        } else {
+                            options.healing_bonus
+                        };
+                        // the client marks modified values with asterisks
+                        if bonus != 0 {
+                            current.push_str(&format!("*{}*", value.saturating_add(bonus).max(0)));
+                        } else {
+                            current.push_str(&digits);
+                        }
+                        i += digits.len() + 1;
+                        continue;
+                    }
+                    Err(_) => current.push(c),
+                }
+            }
+            '{' => {
+                let index: String = chars[i + 1..]
+                    .iter()
+                    .take_while(|c| c.is_digit(10))
+                    .collect();
+                let closed = chars.get(i + 1 + index.len()) == Some(&'}');
+                match index.parse::<usize>() {
+                    Ok(idx) if closed => {
+                        if let Some(value) = options.placeholders.get(idx) {
+                            current.push_str(value);
+                        }
+                        i += index.len() + 2;
+                        continue;
+                    }
+                    _ => current.push(c),
+                }
+            }
+            '\r' => {}
+            _ => current.push(c),
+        }
+        i += 1;
+    }
+    push_run(&mut runs, &mut current, bold, italic);
+
+    CardText {
+        runs: runs,
+        manual_breaks: manual_breaks,
+    }
+}
+
+// Texts with placeholders come with a plain version after the @ sign
+fn select_variant<'a>(text: &'a str, options: &MarkupOptions) -> &'a str {
+    match text.find('@') {
+        Some(idx) => if options.placeholders.is_empty() {
+            &text[idx + 1..]
+        } else {
+            &text[..idx]
+        },
+        None => text,
+    }
+}
+
+fn push_run(runs: &mut Vec<TextRun>, current: &mut String, bold: bool, italic: bool) {
+    if current.is_empty() {
+        return;
+    }
+    runs.push(TextRun {
+        text: current.clone(),
+        bold: bold,
+        italic: italic,
+    });
+    current.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_styles_and_values() {
+        let options = MarkupOptions {
+            spell_damage: 1,
+            ..Default::default()
+        };
+        let text = parse("<b>Battlecry:</b> Deal $2 damage. Restore #3 Health.", &options);
+        assert!(!text.manual_breaks);
+        assert_eq!(text.runs.len(), 2);
+        assert!(text.runs[0].bold);
+        assert_eq!(text.runs[0].text, "Battlecry:");
+        assert_eq!(
+            text.runs[1].text,
+            " Deal *3* damage. Restore 3 Health."
+        );
+    }
+
+    #[test]
+    fn clamp_large_bonuses() {
+        let options = MarkupOptions {
+            spell_damage: i32::max_value(),
+            healing_bonus: i32::min_value(),
+            ..Default::default()
+        };
+        let text = parse("Deal $2 damage. Restore #3 Health.", &options);
+        assert_eq!(
+            text.plain_text(),
+            format!("Deal *{}* damage. Restore *0* Health.", i32::max_value())
+        );
+    }
+
+    #[test]
+    fn parse_placeholders_and_manual_breaks() {
+        let source = "[x]Summon a {0}/{0}\nJade Golem.@Summon a\nJade Golem.";
+        let text = parse(source, &MarkupOptions::default());
+        assert!(text.manual_breaks);
+        assert_eq!(text.plain_text(), "Summon a\nJade Golem.");
+
+        let options = MarkupOptions {
+            placeholders: vec!["2".to_string()],
+            ..Default::default()
+        };
+        let text = parse(source, &options);
+        assert_eq!(text.plain_text(), "Summon a 2/2\nJade Golem.");
+    }
+}
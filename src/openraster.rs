@@ -0,0 +1,106 @@
+use error::{Error, Result};
+use layout::Blend;
+use png::{self, HasParameters};
+use sfml::graphics::Image;
+use std::io::{Seek, Write};
+use utils::ImageUtils;
+use zip::{CompressionMethod, ZipWriter};
+use zip::write::FileOptions;
+
+const THUMBNAIL_SIZE: u32 = 256;
+
+/// Layer of an OpenRaster image, the offset is the position of its top left corner
+pub struct RasterLayer<'a> {
+    pub name: &'a str,
+    pub image: Image,
+    pub x: i32,
+    pub y: i32,
+    pub visible: bool,
+    pub blend: Blend,
+}
+
+/// Writes the layers from bottom to top into an OpenRaster (.ora) file, the merged image is
+/// stored for viewers that cannot read the layers
+pub fn encode_openraster<W: Write + Seek>(
+    width: u32,
+    height: u32,
+    layers: &[RasterLayer],
+    merged: &Image,
+    writer: W,
+) -> Result<()> {
+    let mut zip = ZipWriter::new(writer);
+
+    // the mimetype must be the first entry and must not be compressed
+    zip.start_file(
+        "mimetype",
+        FileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    zip.write_all(b"image/openraster")?;
+
+    let mut stack = String::from("<?xml version='1.0' encoding='UTF-8'?>\n");
+    stack.push_str(&format!(
+        "<image version=\"0.0.3\" w=\"{}\" h=\"{}\">\n<stack>\n",
+        width, height
+    ));
+    // the first layer of the stack is drawn on top
+    for (index, layer) in layers.iter().enumerate().rev() {
+        let source = format!("data/layer{}.png", index);
+        stack.push_str(&format!(
+            "<layer name=\"{}\" src=\"{}\" x=\"{}\" y=\"{}\" visibility=\"{}\" \
+             composite-op=\"{}\"/>\n",
+            escape_xml(layer.name),
+            source,
+            layer.x,
+            layer.y,
+            if layer.visible { "visible" } else { "hidden" },
+            composite_op(layer.blend)
+        ));
+        zip.start_file(source, FileOptions::default())?;
+        write_png(&layer.image, &mut zip)?;
+    }
+    stack.push_str("</stack>\n</image>\n");
+
+    zip.start_file("stack.xml", FileOptions::default())?;
+    zip.write_all(stack.as_bytes())?;
+
+    zip.start_file("mergedimage.png", FileOptions::default())?;
+    write_png(merged, &mut zip)?;
+
+    // thumbnails are at most 256 pixels on their longer side
+    let size = merged.size();
+    let thumbnail_scale = (THUMBNAIL_SIZE as f32 / size.x.max(size.y).max(1) as f32).min(1f32);
+    let mut merged_copy = Image::create_from_pixels(size.x, size.y, merged.pixel_data())
+        .ok_or(Error::SFMLError)?;
+    let thumbnail = merged_copy.resize(
+        ((size.x as f32 * thumbnail_scale) as u32).max(1),
+        ((size.y as f32 * thumbnail_scale) as u32).max(1),
+    )?;
+    zip.start_file("Thumbnail/thumbnail.png", FileOptions::default())?;
+    write_png(&thumbnail, &mut zip)?;
+
+    zip.finish()?;
+    Ok(())
+}
+
+fn write_png<W: Write>(image: &Image, writer: W) -> Result<()> {
+    let mut encoder = png::Encoder::new(writer, image.size().x, image.size().y);
+    encoder.set(png::ColorType::RGBA).set(png::BitDepth::Eight);
+    let mut png_writer = encoder.write_header()?;
+    png_writer.write_image_data(image.pixel_data())?;
+    Ok(())
+}
+
+fn composite_op(blend: Blend) -> &'static str {
+    match blend {
+        Blend::Alpha => "svg:src-over",
+        Blend::Add => "svg:plus",
+        Blend::Multiply => "svg:multiply",
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}